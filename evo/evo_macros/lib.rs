@@ -1,178 +1,561 @@
-use proc_macro2::TokenStream;
+use proc_macro2::{TokenStream, TokenTree};
 use quote::{format_ident, quote};
 use syn::{
-    parse_macro_input, Data, DeriveInput, Fields,
+    parse_macro_input, parse_quote, spanned::Spanned, AttributeArgs, Data, DeriveInput, Field,
+    GenericParam, Generics, Lit, Meta, NestedMeta,
 };
 
 struct BreederSpec {
-    name: syn::Ident,
+    /// Field on the breeder
+    member: syn::Member,
+    /// Field on the genome
+    genome_member: syn::Member,
     ty: syn::Type,
-    weight: f64,
+    mutate: f64,
+    breed: f64,
+    /// Weight of the field in the genome distance
+    distance: f64,
 }
 
-fn remove_attrs(data: &mut Data){
+/// Options given to the macro or the struct
+struct BreederOptions {
+    /// Distance below which genomes are the same species
+    threshold: f64,
+    /// Name of the genome struct
+    genome: syn::Ident,
+    /// Visibility of the genome struct and its fields
+    vis: syn::Visibility,
+    /// Derives added to the genome besides Clone and Debug
+    derives: Vec<syn::Path>,
+}
+
+/// Fields of a struct, or an error pointing at the item
+fn get_fields(data: &Data, ident: &syn::Ident) -> syn::Result<Vec<Field>> {
     match *data {
-        Data::Struct(ref mut data) => match data.fields {
-            Fields::Named(ref mut fields) => fields
-                .named
-                .iter_mut()
-                .map(|f| {
-                    f.attrs.clear();
-                }).collect(),
-            _ => unimplemented!(),
-        },
-        _ => unimplemented!(),
+        Data::Struct(ref data) => Ok(data.fields.iter().cloned().collect()),
+        _ => Err(syn::Error::new(
+            ident.span(),
+            "derive_breeder can only be used on structs",
+        )),
     }
 }
 
-fn get_breeders(data: &Data) -> Vec<BreederSpec> {
-    match *data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => fields
-                .named
-                .iter()
-                .filter(|f| f.attrs.first().unwrap().path.is_ident("breeder"))
-                .map(|f| {
-                    let tokens = &f.attrs.first().unwrap().tokens;
-                    let fstring = tokens.to_string();
-                    let weight: f64 = fstring[1..fstring.len() - 1].parse().unwrap();
-
-                    BreederSpec {
-                        ty: f.ty.clone(),
-                        name: f.ident.clone().unwrap(),
-                        weight,
+/// Strip breeder attributes, which are unknown to the compiler without the derive
+fn remove_attrs(input: &mut DeriveInput) {
+    input.attrs.retain(|a| !a.path.is_ident("breeder"));
+    if let Data::Struct(ref mut data) = input.data {
+        data.fields
+            .iter_mut()
+            .for_each(|f| f.attrs.retain(|a| !a.path.is_ident("breeder")));
+    }
+}
+
+/// Read a number from a literal
+fn parse_number(lit: &Lit) -> syn::Result<f64> {
+    match lit {
+        Lit::Float(f) => f.base10_parse::<f64>(),
+        Lit::Int(i) => i.base10_parse::<f64>(),
+        _ => Err(syn::Error::new(lit.span(), "expected a number")),
+    }
+}
+
+/// Read a probability from a literal
+fn parse_weight(lit: &Lit) -> syn::Result<f64> {
+    let weight = parse_number(lit)?;
+    if !(0.0..=1.0).contains(&weight) {
+        return Err(syn::Error::new(lit.span(), "weight must be between 0 and 1"));
+    }
+    Ok(weight)
+}
+
+/// Parse the `#[breeder(...)]` attribute of a field
+/// Returns None for fields that are not breeders
+///
+/// Supported forms:
+///   #[breeder]
+///   #[breeder(0.5)]
+///   #[breeder(mutate = 0.5, breed = 0.3, distance = 2.0)]
+///   #[breeder(skip)]
+fn parse_field(field: &Field, index: usize, genome_index: usize) -> syn::Result<Option<BreederSpec>> {
+    let attr = match field.attrs.iter().find(|a| a.path.is_ident("breeder")) {
+        Some(attr) => attr,
+        None => return Ok(None),
+    };
+
+    let (member, genome_member) = match &field.ident {
+        Some(ident) => (
+            syn::Member::Named(ident.clone()),
+            syn::Member::Named(ident.clone()),
+        ),
+        None => (
+            syn::Member::Unnamed(syn::Index {
+                index: index as u32,
+                span: field.span(),
+            }),
+            syn::Member::Unnamed(syn::Index {
+                index: genome_index as u32,
+                span: field.span(),
+            }),
+        ),
+    };
+
+    let mut spec = BreederSpec {
+        member,
+        genome_member,
+        ty: field.ty.clone(),
+        mutate: 1.0,
+        breed: 1.0,
+        distance: 1.0,
+    };
+
+    let list = match attr.parse_meta()? {
+        Meta::Path(_) => return Ok(Some(spec)),
+        Meta::List(list) => list,
+        Meta::NameValue(nv) => {
+            return Err(syn::Error::new(
+                nv.span(),
+                "expected #[breeder(...)], not #[breeder = ...]",
+            ))
+        }
+    };
+
+    for nested in list.nested.iter() {
+        match nested {
+            // Single weight for both
+            NestedMeta::Lit(lit) => {
+                let weight = parse_weight(lit)?;
+                spec.mutate = weight;
+                spec.breed = weight;
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("skip") => return Ok(None),
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("mutate") => {
+                spec.mutate = parse_weight(&nv.lit)?;
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("breed") => {
+                spec.breed = parse_weight(&nv.lit)?;
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("distance") => {
+                spec.distance = parse_number(&nv.lit)?;
+            }
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "unknown breeder option, expected `mutate = ..`, `breed = ..`, \
+                     `distance = ..` or `skip`",
+                ))
+            }
+        }
+    }
+
+    Ok(Some(spec))
+}
+
+/// Read a string literal
+fn parse_str(lit: &Lit) -> syn::Result<&syn::LitStr> {
+    match lit {
+        Lit::Str(s) => Ok(s),
+        _ => Err(syn::Error::new(lit.span(), "expected a string")),
+    }
+}
+
+/// Parse the arguments of `#[derive_breeder(...)]` or a struct level `#[breeder(...)]`
+/// The genome defaults to `{Breeder}Genome` with the breeder's visibility
+///
+/// Supported options:
+///   threshold = 1.0
+///   genome = "AntGenome"
+///   vis = "pub(crate)"
+///   derive(PartialEq, Serialize)
+fn parse_options(args: &[NestedMeta], input: &DeriveInput) -> syn::Result<BreederOptions> {
+    let mut options = BreederOptions {
+        threshold: 1.0,
+        genome: format_ident!("{}Genome", input.ident.to_string()),
+        vis: input.vis.clone(),
+        derives: vec![],
+    };
+
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("threshold") => {
+                options.threshold = parse_number(&nv.lit)?;
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("genome") => {
+                options.genome = parse_str(&nv.lit)?.parse()?;
+            }
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("vis") => {
+                options.vis = parse_str(&nv.lit)?.parse()?;
+            }
+            NestedMeta::Meta(Meta::List(list)) if list.path.is_ident("derive") => {
+                for nested in list.nested.iter() {
+                    match nested {
+                        NestedMeta::Meta(Meta::Path(path)) => options.derives.push(path.clone()),
+                        other => {
+                            return Err(syn::Error::new(other.span(), "expected a derive path"))
+                        }
                     }
-                })
-                .collect(),
-            _ => unimplemented!(),
-        },
-        _ => unimplemented!(),
+                }
+            }
+            other => {
+                return Err(syn::Error::new(
+                    other.span(),
+                    "unknown breeder option, expected `threshold = ..`, \
+                     `genome = \"..\"`, `vis = \"..\"` or `derive(..)`",
+                ))
+            }
+        }
     }
+    Ok(options)
 }
 
-fn create_genome(ident: &syn::Ident, breeders: &Vec<BreederSpec>) -> TokenStream {
-    let genome_name = format_ident!("{}Genome", ident.to_string());
-    let fields = breeders.iter().map(|b| {
-        let BreederSpec {name, ty, ..} = b;
+fn get_breeders(fields: &[Field]) -> syn::Result<Vec<BreederSpec>> {
+    let mut breeders = vec![];
+    let mut errors: Option<syn::Error> = None;
 
-        quote! {
-            pub #name: <#ty as Breeder>::Genome,
+    for (i, f) in fields.iter().enumerate() {
+        match parse_field(f, i, breeders.len()) {
+            Ok(Some(spec)) => breeders.push(spec),
+            Ok(None) => (),
+            Err(e) => match errors {
+                Some(ref mut errors) => errors.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(breeders),
+    }
+}
+
+/// Options from the struct level `#[breeder(...)]` attributes
+fn struct_args(input: &DeriveInput) -> syn::Result<Vec<NestedMeta>> {
+    let mut args = vec![];
+    for attr in input.attrs.iter().filter(|a| a.path.is_ident("breeder")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => args.extend(list.nested),
+            other => return Err(syn::Error::new(other.span(), "expected #[breeder(...)]")),
+        }
+    }
+    Ok(args)
+}
+
+/// Whether tokens mention an identifier
+fn mentions(tokens: TokenStream, ident: &syn::Ident) -> bool {
+    tokens.into_iter().any(|t| match t {
+        TokenTree::Ident(ref i) => i == ident,
+        TokenTree::Group(g) => mentions(g.stream(), ident),
+        _ => false,
+    })
+}
+
+fn param_ident(param: &GenericParam) -> &syn::Ident {
+    match param {
+        GenericParam::Type(t) => &t.ident,
+        GenericParam::Lifetime(l) => &l.lifetime.ident,
+        GenericParam::Const(c) => &c.ident,
+    }
+}
+
+/// Generic breeder fields need `Field: Breeder` bounds
+fn add_bounds(generics: &mut Generics, breeders: &[BreederSpec]) {
+    let params: Vec<syn::Ident> = generics.params.iter().map(|p| param_ident(p).clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for b in breeders {
+        let ty = &b.ty;
+        if params.iter().any(|p| mentions(quote!(#ty), p)) {
+            where_clause.predicates.push(parse_quote!(#ty: Breeder));
         }
+    }
+}
+
+/// Generics of the genome, only the parameters its fields use
+fn genome_generics(generics: &Generics, breeders: &[BreederSpec]) -> Generics {
+    let types: Vec<&syn::Type> = breeders.iter().map(|b| &b.ty).collect();
+    let used = |ident: &syn::Ident| types.iter().any(|ty| mentions(quote!(#ty), ident));
+
+    let mut genome = generics.clone();
+    genome.params = generics
+        .params
+        .iter()
+        .filter(|p| used(param_ident(p)))
+        .cloned()
+        .collect();
+
+    // Drop predicates on parameters the genome doesn't have
+    let removed: Vec<&syn::Ident> = generics
+        .params
+        .iter()
+        .map(param_ident)
+        .filter(|i| !used(i))
+        .collect();
+    if let Some(where_clause) = genome.where_clause.as_mut() {
+        where_clause.predicates = where_clause
+            .predicates
+            .iter()
+            .filter(|p| !removed.iter().any(|i| mentions(quote!(#p), i)))
+            .cloned()
+            .collect();
+    }
+
+    add_bounds(&mut genome, breeders);
+    genome
+}
+
+fn create_genome(
+    breeders: &[BreederSpec],
+    named: bool,
+    generics: &Generics,
+    options: &BreederOptions,
+) -> TokenStream {
+    let BreederOptions { genome: genome_name, vis, derives, .. } = options;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let types = breeders.iter().map(|b| {
+        let ty = &b.ty;
+        quote! { <#ty as Breeder>::Genome }
     });
+    let members: Vec<&syn::Member> = breeders.iter().map(|b| &b.genome_member).collect();
+
+    let derive = if derives.is_empty() {
+        quote! {}
+    } else {
+        quote! { #[derive(#(#derives),*)] }
+    };
+
+    let (definition, debug) = if named {
+        let names = &members;
+        let name_strs = members.iter().map(|m| quote!(#m).to_string());
+        (
+            quote! {
+                #vis struct #genome_name #generics #where_clause {
+                    #(#vis #names: #types,)*
+                }
+            },
+            quote! {
+                f.debug_struct(stringify!(#genome_name))
+                    #(.field(#name_strs, &self.#names))*
+                    .finish()
+            },
+        )
+    } else {
+        let names = &members;
+        (
+            quote! {
+                #vis struct #genome_name #generics (#(#vis #types,)*) #where_clause;
+            },
+            quote! {
+                f.debug_tuple(stringify!(#genome_name))
+                    #(.field(&self.#names))*
+                    .finish()
+            },
+        )
+    };
+
+    // Clone and Debug by hand, derives would require them on the breeders
     let code = quote! {
-        #[derive(Clone, Debug)]
-        pub struct #genome_name {
-            #(#fields)*
+        #derive
+        #definition
+
+        impl #impl_generics Clone for #genome_name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                Self {
+                    #(#members: self.#members.clone(),)*
+                }
+            }
+        }
+
+        impl #impl_generics std::fmt::Debug for #genome_name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                #debug
+            }
         }
     };
-    code.into()
+    code
 }
 
-fn create_impl(ident: &syn::Ident, breeders: &Vec<BreederSpec>) -> TokenStream {
+fn create_impl(
+    ident: &syn::Ident,
+    breeders: &[BreederSpec],
+    generics: &Generics,
+    genome_generics: &Generics,
+    options: &BreederOptions,
+) -> TokenStream {
     let mutate = breeders.iter().map(|b| {
-        let BreederSpec {name, weight, .. } = b;
+        let BreederSpec {member, genome_member, mutate, .. } = b;
 
         quote! {
-            #name: if rand::random::<f64>() < #weight {
-                self.#name.mutate(&g.#name)
+            #genome_member: if rand::random::<f64>() < #mutate {
+                self.#member.mutate(&g.#genome_member)
             } else {
-                g.#name.clone()
+                g.#genome_member.clone()
             },
         }
     });
 
     let breed = breeders.iter().map(|b| {
-        let BreederSpec {name, weight, .. } = b;
+        let BreederSpec {member, genome_member, breed, .. } = b;
 
         quote! {
-            #name: if rand::random::<f64>() < #weight {
-                self.#name.breed(&g1.#name, &g2.#name)
+            #genome_member: if rand::random::<f64>() < #breed {
+                self.#member.breed(&g1.#genome_member, &g2.#genome_member)
             } else if rand::random::<f64>() < 0.5 {
-                g1.#name.clone()
+                g1.#genome_member.clone()
             } else {
-                g2.#name.clone()
+                g2.#genome_member.clone()
             },
         }
     });
 
     let random = breeders.iter().map(|b| {
-        let BreederSpec {name, ..} = b;
+        let BreederSpec {member, genome_member, ..} = b;
         quote! {
-            #name: self.#name.random(),
+            #genome_member: self.#member.random(),
         }
     });
 
-    let is_same = breeders.iter().map(|b| {
-        let BreederSpec {name, ..} = b;
+    let distance = breeders.iter().map(|b| {
+        let BreederSpec {member, genome_member, distance, ..} = b;
         quote! {
-            self.#name.is_same(&g1.#name, &g2.#name)
+            #distance * self.#member.distance(&g1.#genome_member, &g2.#genome_member)
         }
     });
 
-    let genome = format_ident!("{}Genome", ident.to_string());
+    let size = breeders.iter().map(|b| {
+        let BreederSpec {member, genome_member, ..} = b;
+        quote! {
+            self.#member.size(&g.#genome_member)
+        }
+    });
+
+    let threshold = options.threshold;
+
+    let mut generics = generics.clone();
+    add_bounds(&mut generics, breeders);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (genome_impl, genome_ty, genome_where) = genome_generics.split_for_impl();
+
+    let genome = &options.genome;
     let code = quote! {
-        impl Breeder for #ident {
-            type Genome = #genome;
+        impl #impl_generics Breeder for #ident #ty_generics #where_clause {
+            type Genome = #genome #genome_ty;
 
             fn mutate(&self, g: &Self::Genome) -> Self::Genome {
-                Self::Genome {
+                #genome {
                     #(#mutate)*
                 }
             }
 
             fn breed(&self, g1: &Self::Genome, g2: &Self::Genome) -> Self::Genome {
-                Self::Genome {
+                #genome {
                     #(#breed)*
                 }
             }
 
             fn random(&self) -> Self::Genome {
-                Self::Genome {
+                #genome {
                     #(#random)*
                 }
             }
 
+            fn distance(&self, g1: &Self::Genome, g2: &Self::Genome) -> f64 {
+                0.0 #(+ #distance)*
+            }
+
             fn is_same(&self, g1: &Self::Genome, g2: &Self::Genome) -> bool {
-                true #(&& #is_same)*
+                self.distance(g1, g2) < #threshold
+            }
+
+            fn size(&self, g: &Self::Genome) -> usize {
+                0 #(+ #size)*
             }
         }
 
         // gene.fn style calls
-        impl #genome {
-            fn mutate(&self, breeder: &#ident) -> Self {
+        impl #genome_impl #genome #genome_ty #genome_where {
+            pub fn mutate<__B: Breeder<Genome = Self>>(&self, breeder: &__B) -> Self {
                 breeder.mutate(&self)
             }
-            fn breed(&self, breeder: &#ident, other: &Self) -> Self {
+            pub fn breed<__B: Breeder<Genome = Self>>(&self, breeder: &__B, other: &Self) -> Self {
                 breeder.breed(&self, other)
             }
-            fn random(breeder: &#ident) -> Self {
+            pub fn random<__B: Breeder<Genome = Self>>(breeder: &__B) -> Self {
                 breeder.random()
             }
-            fn is_same(&self, breeder: &#ident, other: &Self) -> bool {
+            pub fn distance<__B: Breeder<Genome = Self>>(&self, breeder: &__B, other: &Self) -> f64 {
+                breeder.distance(self, other)
+            }
+            pub fn is_same<__B: Breeder<Genome = Self>>(&self, breeder: &__B, other: &Self) -> bool {
                 breeder.is_same(self, other)
             }
         }
     };
-    code.into()
+    code
 }
 
+/// Genome struct and Breeder impl for a breeder struct
+fn expand(input: &DeriveInput, args: &[NestedMeta]) -> syn::Result<TokenStream> {
+    let mut args = args.to_vec();
+    args.extend(struct_args(input)?);
+    let options = parse_options(&args, input)?;
+
+    let fields = get_fields(&input.data, &input.ident)?;
+    let named = fields.iter().all(|f| f.ident.is_some());
+    let breeders = get_breeders(&fields)?;
+
+    let genome_generics = genome_generics(&input.generics, &breeders);
+    let genome = create_genome(&breeders, named, &genome_generics, &options);
+    let breeder_impl = create_impl(
+        &input.ident,
+        &breeders,
+        &input.generics,
+        &genome_generics,
+        &options,
+    );
+
+    Ok(quote! {
+        #genome
+        #breeder_impl
+    })
+}
+
+/// Derive `Breeder` for a struct of breeders
+///
+/// Fields marked `#[breeder(...)]` become the fields of a generated genome,
+/// struct level `#[breeder(...)]` attributes set the genome options.
+#[proc_macro_derive(Breeder, attributes(breeder))]
+pub fn derive(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+
+    match expand(&input, &[]) {
+        Ok(code) => code.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Attribute form of `#[derive(Breeder)]`, options are given as arguments
 #[proc_macro_attribute]
 pub fn derive_breeder(
-    _: proc_macro::TokenStream,
+    attr: proc_macro::TokenStream,
     item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
     let mut input = parse_macro_input!(item as DeriveInput);
 
-    let breeders = get_breeders(&input.data);
-    let breeder_impl = create_impl(&input.ident, &breeders);
-    let genome = create_genome(&input.ident, &breeders);
+    let result = expand(&input, &args);
+    remove_attrs(&mut input);
 
-    remove_attrs(&mut input.data);
-
-    let code = quote! {
-        #input
-        #genome
-        #breeder_impl
+    let code = match result {
+        Ok(code) => quote! {
+            #input
+            #code
+        },
+        Err(e) => {
+            let error = e.to_compile_error();
+            quote! {
+                #input
+                #error
+            }
+        }
     };
     // println!("{}", code.to_string());
     code.into()
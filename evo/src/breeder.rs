@@ -2,6 +2,9 @@ use crate::utils::*;
 use std::collections::HashMap;
 use std::fmt::Debug;
 
+mod container;
+pub use container::*;
+
 //////////////////////////////////
 /// Breeder Trait
 /// Provides methods to create new and mix Genes
@@ -11,8 +14,108 @@ pub trait Breeder {
     fn mutate(&self, gene: &Self::Genome) -> Self::Genome;
     fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome;
     fn random(&self) -> Self::Genome;
-    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool;
+
+    /// Distance between two genomes, 0 when identical
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64;
+
+    /// Whether two genomes are the same species
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        self.distance(gene1, gene2) < 1.0
+    }
+
+    /// Number of genes in a genome
+    fn size(&self, _gene: &Self::Genome) -> usize {
+        1
+    }
+}
+
+//////////////////////////////////
+/// Mutation
+/// Distribution of the perturbation added to a float
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mutation {
+    /// Uniform in [-delta, delta]
+    Uniform,
+    /// Normal with standard deviation delta
+    Gaussian,
+    /// Cauchy with scale delta, mostly small with rare large jumps
+    Cauchy,
+    /// Polynomial mutation with a distribution index, scaled to the bounds
+    Polynomial(f64),
 }
+
+impl Mutation {
+    /// Perturb a value, keeping it within [min, max]
+    pub fn apply(&self, x: f64, delta: f64, min: f64, max: f64) -> f64 {
+        let dx = match *self {
+            Mutation::Uniform => random_d(delta),
+            Mutation::Gaussian => delta * random_normal(),
+            Mutation::Cauchy => delta * (std::f64::consts::PI * (random() - 0.5)).tan(),
+            Mutation::Polynomial(eta) => {
+                let u = random();
+                let dq = if u < 0.5 {
+                    (2.0 * u).powf(1.0 / (eta + 1.0)) - 1.0
+                } else {
+                    1.0 - (2.0 * (1.0 - u)).powf(1.0 / (eta + 1.0))
+                };
+                dq * (max - min)
+            }
+        };
+        clamp(x + dx, min, max)
+    }
+}
+
+//////////////////////////////////
+/// Crossover
+/// Method of combining two floats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Crossover {
+    /// Multi point crossover, VecBreeder flips parents at flip_rate
+    Flip,
+    /// Each value from either parent
+    Uniform,
+    /// Random weighted average of both parents
+    Arithmetic,
+    /// BLX-alpha, uniform in the parents' range extended by alpha
+    Blend(f64),
+    /// Simulated binary crossover with a distribution index
+    Sbx(f64),
+}
+
+impl Crossover {
+    /// Combine two values, keeping the result within [min, max]
+    pub fn apply(&self, x1: f64, x2: f64, min: f64, max: f64) -> f64 {
+        let x = match *self {
+            Crossover::Flip | Crossover::Uniform => {
+                if random() < 0.5 {
+                    x1
+                } else {
+                    x2
+                }
+            }
+            Crossover::Arithmetic => {
+                let r = random();
+                x1 * r + x2 * (1. - r)
+            }
+            Crossover::Blend(alpha) => {
+                let d = (x1 - x2).abs();
+                random_clamp(x1.min(x2) - alpha * d, x1.max(x2) + alpha * d)
+            }
+            Crossover::Sbx(eta) => {
+                let u = random();
+                let beta = if u <= 0.5 {
+                    (2.0 * u).powf(1.0 / (eta + 1.0))
+                } else {
+                    (1.0 / (2.0 * (1.0 - u))).powf(1.0 / (eta + 1.0))
+                };
+                let sign = if random() < 0.5 { 1.0 } else { -1.0 };
+                0.5 * ((x1 + x2) + sign * beta * (x1 - x2))
+            }
+        };
+        clamp(x, min, max)
+    }
+}
+
 //////////////////////////////////
 /// VecBreeder
 /// Breeder that breeds lists of floats
@@ -24,6 +127,8 @@ pub struct VecBreeder {
     pub mutate_rate: f64,
     pub flip_rate: f64,
     pub is_same_threshold: f64,
+    pub mutation: Mutation,
+    pub crossover: Crossover,
 }
 
 impl Default for VecBreeder {
@@ -35,7 +140,9 @@ impl Default for VecBreeder {
             delta: 0.5,
             mutate_rate: 2.0,
             flip_rate: 1.0,
-            is_same_threshold: 0.5
+            is_same_threshold: 0.5,
+            mutation: Mutation::Uniform,
+            crossover: Crossover::Flip,
         }
     }
 }
@@ -47,7 +154,7 @@ impl Breeder for VecBreeder {
         gene.iter()
             .map(|x| {
                 if random() < self.mutate_rate / (self.size as f64) {
-                    clamp(x + random_d(self.delta), self.min, self.max)
+                    self.mutation.apply(*x, self.delta, self.min, self.max)
                 } else {
                     *x
                 }
@@ -56,6 +163,14 @@ impl Breeder for VecBreeder {
     }
 
     fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        if self.crossover != Crossover::Flip {
+            return gene1
+                .iter()
+                .zip(gene2)
+                .map(|(g1, g2)| self.crossover.apply(*g1, *g2, self.min, self.max))
+                .collect();
+        }
+
         let mut flip = false;
         gene1
             .iter()
@@ -79,11 +194,18 @@ impl Breeder for VecBreeder {
             .collect()
     }
 
-    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
         gene1.iter().zip(gene2).fold(0f64, |s, (x, y)| {
             s + (x - y) * (x - y) / (self.max - self.min) / (self.max - self.min)
         }) / (self.size as f64)
-            < self.is_same_threshold
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        self.distance(gene1, gene2) < self.is_same_threshold
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.len()
     }
 }
 
@@ -94,26 +216,31 @@ pub struct FloatBreeder {
     pub min: f64,
     pub max: f64,
     pub delta: f64,
+    pub mutation: Mutation,
+    pub crossover: Crossover,
 }
 
 impl Breeder for FloatBreeder {
     type Genome = f64;
 
     fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
-        (gene + random_d(self.delta)).clamp(self.min, self.max)
+        self.mutation.apply(*gene, self.delta, self.min, self.max)
     }
 
     fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
-        let r = random();
-        gene1 * r + gene2 * (1. - r)
+        self.crossover.apply(*gene1, *gene2, self.min, self.max)
     }
 
     fn random(&self) -> Self::Genome {
         random_clamp(self.min, self.max)
     }
 
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        (gene1 - gene2).abs()
+    }
+
     fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
-        (gene1 - gene2).abs() < self.delta * 2.0
+        self.distance(gene1, gene2) < self.delta * 2.0
     }
 }
 
@@ -123,6 +250,345 @@ impl Default for FloatBreeder {
             min: -1.0,
             max: 1.0,
             delta: 0.1,
+            mutation: Mutation::Uniform,
+            crossover: Crossover::Arithmetic,
+        }
+    }
+}
+
+//////////////////////////////////
+/// Int Breeder
+/// Swapped bounds and negative deltas are taken as their ordered and absolute values
+pub struct IntBreeder {
+    pub min: i64,
+    pub max: i64,
+    pub delta: i64,
+}
+
+impl IntBreeder {
+    /// Bounds in order
+    fn bounds(&self) -> (i64, i64) {
+        (self.min.min(self.max), self.min.max(self.max))
+    }
+}
+
+impl Breeder for IntBreeder {
+    type Genome = i64;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        let delta = self.delta.unsigned_abs();
+        let d = random_i(2 * delta as usize + 1) as i64 - delta as i64;
+        let (min, max) = self.bounds();
+        clamp(gene + d, min, max)
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        let lo = *gene1.min(gene2);
+        let hi = *gene1.max(gene2);
+        lo + random_i((hi - lo) as usize + 1) as i64
+    }
+
+    fn random(&self) -> Self::Genome {
+        let (min, max) = self.bounds();
+        min + random_i((max - min) as usize + 1) as i64
+    }
+
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        (gene1 - gene2).abs() as f64
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        self.distance(gene1, gene2) <= self.delta.unsigned_abs() as f64
+    }
+}
+
+impl Default for IntBreeder {
+    fn default() -> Self {
+        Self {
+            min: 0,
+            max: 10,
+            delta: 1,
+        }
+    }
+}
+
+//////////////////////////////////
+/// Bool Breeder
+/// Mutation flips the gene
+pub struct BoolBreeder {
+    pub true_rate: f64,
+}
+
+impl Breeder for BoolBreeder {
+    type Genome = bool;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        !gene
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        if random() < 0.5 {
+            *gene1
+        } else {
+            *gene2
+        }
+    }
+
+    fn random(&self) -> Self::Genome {
+        random() < self.true_rate
+    }
+
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        if gene1 == gene2 {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+impl Default for BoolBreeder {
+    fn default() -> Self {
+        Self { true_rate: 0.5 }
+    }
+}
+
+//////////////////////////////////
+/// BitVec Breeder
+/// Breeds lists of bools, such as masks
+pub struct BitVecBreeder {
+    pub size: usize,
+    pub true_rate: f64,
+    pub mutate_rate: f64,
+    pub flip_rate: f64,
+    pub is_same_threshold: f64,
+}
+
+impl Breeder for BitVecBreeder {
+    type Genome = Vec<bool>;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        gene.iter()
+            .map(|x| {
+                if random() < self.mutate_rate / (self.size as f64) {
+                    !x
+                } else {
+                    *x
+                }
+            })
+            .collect()
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        let mut flip = false;
+        gene1
+            .iter()
+            .zip(gene2)
+            .map(|(g1, g2)| {
+                if random() < self.flip_rate / (self.size as f64) {
+                    flip = !flip;
+                }
+                if flip {
+                    *g1
+                } else {
+                    *g2
+                }
+            })
+            .collect()
+    }
+
+    fn random(&self) -> Self::Genome {
+        (0..self.size).map(|_| random() < self.true_rate).collect()
+    }
+
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        gene1.iter().zip(gene2).filter(|(x, y)| x != y).count() as f64 / (self.size as f64)
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        self.distance(gene1, gene2) < self.is_same_threshold
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.len()
+    }
+}
+
+impl Default for BitVecBreeder {
+    fn default() -> Self {
+        Self {
+            size: 8,
+            true_rate: 0.5,
+            mutate_rate: 1.0,
+            flip_rate: 1.0,
+            is_same_threshold: 0.25,
+        }
+    }
+}
+
+//////////////////////////////////
+/// Permutation Breeder
+/// Breeds orderings of 0..size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PermutationCrossover {
+    /// Keep a slice of one parent, fill the rest in the other's order
+    Order,
+    /// Partially mapped crossover
+    Pmx,
+}
+
+pub struct PermutationBreeder {
+    pub size: usize,
+    pub mutate_rate: f64,
+    pub crossover: PermutationCrossover,
+    pub is_same_threshold: f64,
+}
+
+impl PermutationBreeder {
+    /// Random slice bounds [a, b)
+    fn slice(&self) -> (usize, usize) {
+        if self.size == 0 {
+            return (0, 0);
+        }
+        let a = random_i(self.size);
+        let b = random_i(self.size);
+        (a.min(b), a.max(b) + 1)
+    }
+}
+
+impl Breeder for PermutationBreeder {
+    type Genome = Vec<usize>;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        let mut gene = gene.clone();
+        if self.size == 0 {
+            return gene;
+        }
+
+        // Always make at least one swap
+        let swaps = 1 + (0..self.size)
+            .filter(|_| random() < self.mutate_rate / (self.size as f64))
+            .count();
+        for _ in 0..swaps {
+            let a = random_i(self.size);
+            let b = random_i(self.size);
+            gene.swap(a, b);
+        }
+        gene
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        let (a, b) = self.slice();
+        match self.crossover {
+            PermutationCrossover::Order => {
+                let kept = &gene1[a..b];
+                let mut rest = gene2.iter().filter(|x| !kept.contains(x));
+                (0..self.size)
+                    .map(|i| {
+                        if i >= a && i < b {
+                            gene1[i]
+                        } else {
+                            *rest.next().unwrap()
+                        }
+                    })
+                    .collect()
+            }
+            PermutationCrossover::Pmx => {
+                let mut child = gene2.clone();
+                child[a..b].copy_from_slice(&gene1[a..b]);
+
+                // Follow the mapping until the value is outside the slice
+                for i in (0..a).chain(b..self.size) {
+                    let mut x = gene2[i];
+                    while let Some(p) = gene1[a..b].iter().position(|y| *y == x) {
+                        x = gene2[a + p];
+                    }
+                    child[i] = x;
+                }
+                child
+            }
+        }
+    }
+
+    fn random(&self) -> Self::Genome {
+        let mut gene: Vec<usize> = (0..self.size).collect();
+        gene.shuffle();
+        gene
+    }
+
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        if self.size == 0 {
+            return 0.0;
+        }
+        gene1.iter().zip(gene2).filter(|(x, y)| x != y).count() as f64 / (self.size as f64)
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        self.distance(gene1, gene2) < self.is_same_threshold
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.len()
+    }
+}
+
+impl Default for PermutationBreeder {
+    fn default() -> Self {
+        Self {
+            size: 8,
+            mutate_rate: 1.0,
+            crossover: PermutationCrossover::Order,
+            is_same_threshold: 0.5,
+        }
+    }
+}
+
+//////////////////////////////////
+/// Choice Breeder
+/// Picks one of a fixed set of values, such as enum variants
+pub struct ChoiceBreeder<T> {
+    pub choices: Vec<T>,
+}
+
+impl<T> ChoiceBreeder<T> {
+    /// Panics without any choices
+    pub fn new(choices: Vec<T>) -> Self {
+        assert!(!choices.is_empty(), "ChoiceBreeder needs at least one choice");
+        Self { choices }
+    }
+}
+
+impl<T> Breeder for ChoiceBreeder<T>
+where
+    T: Clone + Debug + PartialEq,
+{
+    type Genome = T;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        let others: Vec<&T> = self.choices.iter().filter(|c| *c != gene).collect();
+        if others.is_empty() {
+            return gene.clone();
+        }
+        (*others.sample()).clone()
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        if random() < 0.5 {
+            gene1.clone()
+        } else {
+            gene2.clone()
+        }
+    }
+
+    fn random(&self) -> Self::Genome {
+        self.choices.sample().clone()
+    }
+
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        if gene1 == gene2 {
+            0.0
+        } else {
+            1.0
         }
     }
 }
@@ -130,7 +596,7 @@ impl Default for FloatBreeder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use evo_macros::derive_breeder;
+    use evo_macros::{derive_breeder, Breeder};
 
     #[derive_breeder]
     struct MyBreeder {
@@ -141,11 +607,212 @@ mod tests {
         v: VecBreeder,
     }
 
+    #[derive_breeder]
+    struct NamedArgs {
+        #[breeder(mutate = 1.0, breed = 0)]
+        f: FloatBreeder,
+
+        #[breeder(skip)]
+        skipped: FloatBreeder,
+
+        #[breeder]
+        i: IntBreeder,
+    }
+
+    #[derive_breeder(threshold = 0.5, genome = "WeightedGenes", vis = "pub(crate)", derive(PartialEq))]
+    struct Weighted {
+        #[breeder(distance = 0.1)]
+        a: FloatBreeder,
+
+        #[breeder(distance = 1.0)]
+        b: FloatBreeder,
+    }
+
+    /// Generic breeder through the derive
+    #[derive(Breeder)]
+    #[allow(dead_code)]
+    #[breeder(genome = "CreatureGenes")]
+    struct Creature<B: Breeder, T> {
+        #[breeder(0.5)]
+        brain: B,
+
+        #[breeder]
+        legs: OptionBreeder<IntBreeder>,
+
+        name: T,
+    }
+
+    #[derive_breeder]
+    struct TupleBreeder(#[breeder(0.5)] FloatBreeder, usize, #[breeder] BoolBreeder);
+
     #[test]
     fn derive() {
-        let m = MyBreeder {
+        let _ = MyBreeder {
             f: FloatBreeder::default(),
             v: VecBreeder::default(),
         };
     }
+
+    #[test]
+    fn derive_named_args() {
+        let b = NamedArgs {
+            f: FloatBreeder::default(),
+            skipped: FloatBreeder {
+                min: 5.0,
+                max: 6.0,
+                ..FloatBreeder::default()
+            },
+            i: IntBreeder::default(),
+        };
+        let g = b.random();
+        let m = b.mutate(&g);
+        assert_ne!(g.f, m.f);
+
+        // Breeding never mixes f, only picks a parent
+        let c = b.breed(&g, &m);
+        assert!(c.f == g.f || c.f == m.f);
+
+        // The skipped field is left alone
+        assert_eq!((b.skipped.min, b.skipped.max), (5.0, 6.0));
+    }
+
+    #[test]
+    fn derive_distance() {
+        let w = Weighted {
+            a: FloatBreeder::default(),
+            b: FloatBreeder::default(),
+        };
+        let g1 = WeightedGenes { a: 0.0, b: 0.0 };
+        let g2 = WeightedGenes { a: 1.0, b: 0.0 };
+        let g3 = WeightedGenes { a: 0.0, b: 1.0 };
+        assert_eq!(g1, g1.clone());
+
+        assert!((w.distance(&g1, &g2) - 0.1).abs() < 1e-9);
+        assert!(w.is_same(&g1, &g2));
+        assert!(!w.is_same(&g1, &g3));
+    }
+
+    #[test]
+    fn derive_generic() {
+        let c = Creature {
+            brain: FloatBreeder::default(),
+            legs: OptionBreeder::new(IntBreeder::default()),
+            name: "ant",
+        };
+        let g: CreatureGenes<FloatBreeder> = c.random();
+        let m = g.mutate(&c);
+        assert!(c.distance(&g, &g) == 0.0);
+        assert!(format!("{:?}", m).starts_with("CreatureGenes"));
+        assert_eq!(c.name, "ant");
+    }
+
+    #[test]
+    fn derive_tuple() {
+        let b = TupleBreeder(FloatBreeder::default(), 3, BoolBreeder::default());
+        let g: TupleBreederGenome = b.random();
+        let m = b.mutate(&g);
+        assert_ne!(g.1, m.1);
+        assert!(b.is_same(&g, &g));
+
+        // The plain field stays on the breeder
+        let _ = b.breed(&g, &m);
+        assert_eq!(b.1, 3);
+    }
+
+    #[test]
+    fn mutation_in_bounds() {
+        let ops = vec![
+            Mutation::Uniform,
+            Mutation::Gaussian,
+            Mutation::Cauchy,
+            Mutation::Polynomial(20.0),
+        ];
+        for op in ops {
+            for _ in 0..100 {
+                let x = op.apply(0.9, 0.5, 0.0, 1.0);
+                assert!((0.0..=1.0).contains(&x), "{:?} gave {}", op, x);
+            }
+        }
+    }
+
+    #[test]
+    fn crossover_ranges() {
+        for _ in 0..100 {
+            let x = Crossover::Arithmetic.apply(0.2, 0.4, 0.0, 1.0);
+            assert!((0.2..=0.4).contains(&x));
+
+            let x = Crossover::Blend(0.5).apply(0.2, 0.4, 0.0, 1.0);
+            assert!((0.1..=0.5).contains(&x));
+
+            let x = Crossover::Uniform.apply(0.2, 0.4, 0.0, 1.0);
+            assert!(x == 0.2 || x == 0.4);
+
+            let x = Crossover::Sbx(2.0).apply(0.2, 0.4, 0.0, 1.0);
+            assert!((0.0..=1.0).contains(&x));
+        }
+
+        let b = VecBreeder {
+            crossover: Crossover::Blend(0.0),
+            ..VecBreeder::default()
+        };
+        let child = b.breed(&vec![0.0; 100], &vec![1.0; 100]);
+        assert_eq!(child.len(), 100);
+    }
+
+    #[test]
+    fn int_in_bounds() {
+        let b = IntBreeder::default();
+        for _ in 0..100 {
+            let g = b.mutate(&b.random());
+            assert!(g >= b.min && g <= b.max);
+            let g = b.breed(&2, &5);
+            assert!((2..=5).contains(&g));
+        }
+
+        // Swapped bounds and a negative delta
+        let b = IntBreeder {
+            min: 10,
+            max: 0,
+            delta: -3,
+        };
+        for _ in 0..100 {
+            let g = b.mutate(&b.random());
+            assert!((0..=10).contains(&g));
+        }
+        assert!(b.is_same(&0, &3));
+    }
+
+    #[test]
+    fn permutation_crossover() {
+        for crossover in [PermutationCrossover::Order, PermutationCrossover::Pmx] {
+            let b = PermutationBreeder {
+                crossover,
+                ..PermutationBreeder::default()
+            };
+            for _ in 0..100 {
+                let mut child = b.breed(&b.random(), &b.mutate(&b.random()));
+                child.sort();
+                assert_eq!(child, (0..b.size).collect::<Vec<_>>());
+            }
+
+            // Empty permutations
+            let b = PermutationBreeder { size: 0, ..b };
+            assert!(b.mutate(&b.random()).is_empty());
+            assert!(b.breed(&vec![], &vec![]).is_empty());
+            assert!(b.is_same(&vec![], &vec![]));
+        }
+    }
+
+    #[test]
+    fn choice() {
+        let b = ChoiceBreeder::new(vec!['a', 'b', 'c']);
+        assert_ne!(b.mutate(&'a'), 'a');
+        assert!(b.choices.contains(&b.random()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn choice_empty() {
+        ChoiceBreeder::<char>::new(vec![]);
+    }
 }
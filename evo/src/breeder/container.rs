@@ -0,0 +1,374 @@
+use super::Breeder;
+use crate::utils::*;
+use std::convert::TryInto;
+
+//////////////////////////////////
+/// Option Breeder
+/// Gene that can be present or absent
+pub struct OptionBreeder<B: Breeder> {
+    pub breeder: B,
+    /// Chance a random gene is present
+    pub present_rate: f64,
+    /// Chance a mutation adds or removes the gene instead of mutating it
+    pub toggle_rate: f64,
+}
+
+impl<B: Breeder> OptionBreeder<B> {
+    pub fn new(breeder: B) -> Self {
+        Self {
+            breeder,
+            present_rate: 0.5,
+            toggle_rate: 0.1,
+        }
+    }
+}
+
+impl<B: Breeder> Breeder for OptionBreeder<B> {
+    type Genome = Option<B::Genome>;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        match gene {
+            Some(_) if random() < self.toggle_rate => None,
+            Some(g) => Some(self.breeder.mutate(g)),
+            None if random() < self.toggle_rate => Some(self.breeder.random()),
+            None => None,
+        }
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        match (gene1, gene2) {
+            (Some(g1), Some(g2)) => Some(self.breeder.breed(g1, g2)),
+            _ if random() < 0.5 => gene1.clone(),
+            _ => gene2.clone(),
+        }
+    }
+
+    fn random(&self) -> Self::Genome {
+        if random() < self.present_rate {
+            Some(self.breeder.random())
+        } else {
+            None
+        }
+    }
+
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        match (gene1, gene2) {
+            (Some(g1), Some(g2)) => self.breeder.distance(g1, g2),
+            (None, None) => 0.0,
+            _ => 1.0,
+        }
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        match (gene1, gene2) {
+            (Some(g1), Some(g2)) => self.breeder.is_same(g1, g2),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.as_ref().map_or(0, |g| self.breeder.size(g))
+    }
+}
+
+//////////////////////////////////
+/// Array Breeder
+/// Fixed number of genes bred by the same breeder
+pub struct ArrayBreeder<B: Breeder, const N: usize> {
+    pub breeder: B,
+    /// Expected number of genes mutated
+    pub mutate_rate: f64,
+    /// Chance matching genes are bred instead of picked from a parent
+    pub breed_rate: f64,
+}
+
+impl<B: Breeder, const N: usize> ArrayBreeder<B, N> {
+    pub fn new(breeder: B) -> Self {
+        Self {
+            breeder,
+            mutate_rate: 1.0,
+            breed_rate: 0.5,
+        }
+    }
+}
+
+impl<B: Breeder, const N: usize> Breeder for ArrayBreeder<B, N> {
+    type Genome = [B::Genome; N];
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        gene.iter()
+            .map(|g| {
+                if random() < self.mutate_rate / (N as f64) {
+                    self.breeder.mutate(g)
+                } else {
+                    g.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        gene1
+            .iter()
+            .zip(gene2.iter())
+            .map(|(g1, g2)| breed_pair(&self.breeder, self.breed_rate, g1, g2))
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+
+    fn random(&self) -> Self::Genome {
+        (0..N)
+            .map(|_| self.breeder.random())
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Mean distance of the genes
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        gene1
+            .iter()
+            .zip(gene2.iter())
+            .map(|(g1, g2)| self.breeder.distance(g1, g2))
+            .sum::<f64>()
+            / (N.max(1) as f64)
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        gene1
+            .iter()
+            .zip(gene2.iter())
+            .all(|(g1, g2)| self.breeder.is_same(g1, g2))
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.iter().map(|g| self.breeder.size(g)).sum()
+    }
+}
+
+//////////////////////////////////
+/// Variable Vec Breeder
+/// Lists of genes that grow and shrink by mutation
+pub struct VarVecBreeder<B: Breeder> {
+    pub breeder: B,
+    pub min_len: usize,
+    pub max_len: usize,
+    /// Length of random genomes
+    pub initial_len: usize,
+    /// Expected number of genes mutated
+    pub mutate_rate: f64,
+    /// Chance of inserting a random gene on mutation
+    pub insert_rate: f64,
+    /// Chance of deleting a gene on mutation
+    pub delete_rate: f64,
+    /// Chance matching genes are bred instead of picked from a parent
+    pub breed_rate: f64,
+    pub is_same_threshold: f64,
+}
+
+impl<B: Breeder> VarVecBreeder<B> {
+    pub fn new(breeder: B) -> Self {
+        Self {
+            breeder,
+            min_len: 0,
+            max_len: 10,
+            initial_len: 2,
+            mutate_rate: 1.0,
+            insert_rate: 0.1,
+            delete_rate: 0.1,
+            breed_rate: 0.5,
+            is_same_threshold: 0.5,
+        }
+    }
+}
+
+impl<B: Breeder> Breeder for VarVecBreeder<B> {
+    type Genome = Vec<B::Genome>;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        let len = gene.len().max(1) as f64;
+        let mut gene: Self::Genome = gene
+            .iter()
+            .map(|g| {
+                if random() < self.mutate_rate / len {
+                    self.breeder.mutate(g)
+                } else {
+                    g.clone()
+                }
+            })
+            .collect();
+
+        if gene.len() < self.max_len && random() < self.insert_rate {
+            gene.insert(random_i(gene.len() + 1), self.breeder.random());
+        }
+
+        if gene.len() > self.min_len && random() < self.delete_rate {
+            gene.remove(random_i(gene.len()));
+        }
+        gene
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        // Shared part is bred, the tail comes from the longer parent
+        let (long, short) = if gene1.len() >= gene2.len() {
+            (gene1, gene2)
+        } else {
+            (gene2, gene1)
+        };
+        let len = if random() < 0.5 { long.len() } else { short.len() };
+
+        long.iter()
+            .take(len)
+            .enumerate()
+            .map(|(i, g)| match short.get(i) {
+                Some(s) => breed_pair(&self.breeder, self.breed_rate, g, s),
+                None => g.clone(),
+            })
+            .collect()
+    }
+
+    fn random(&self) -> Self::Genome {
+        (0..self.initial_len.max(self.min_len).min(self.max_len))
+            .map(|_| self.breeder.random())
+            .collect()
+    }
+
+    /// Fraction of genes that are not the same
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        let len = gene1.len().max(gene2.len());
+        if len == 0 {
+            return 0.0;
+        }
+        let matching = gene1
+            .iter()
+            .zip(gene2)
+            .filter(|(g1, g2)| self.breeder.is_same(g1, g2))
+            .count();
+        1.0 - (matching as f64 / len as f64)
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        self.distance(gene1, gene2) < self.is_same_threshold
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.iter().map(|g| self.breeder.size(g)).sum()
+    }
+}
+
+/// Breed two genes or pick either of them
+fn breed_pair<B: Breeder>(breeder: &B, rate: f64, g1: &B::Genome, g2: &B::Genome) -> B::Genome {
+    if random() < rate {
+        breeder.breed(g1, g2)
+    } else if random() < 0.5 {
+        g1.clone()
+    } else {
+        g2.clone()
+    }
+}
+
+//////////////////////////////////
+/// Tuple Breeders
+/// Mutation changes one element, breeding breeds all of them
+macro_rules! tuple_breeder {
+    ($($B:ident $i:tt),+) => {
+        impl<$($B: Breeder),+> Breeder for ($($B,)+) {
+            type Genome = ($($B::Genome,)+);
+
+            fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+                let k = random_i([$($i),+].len());
+                ($(
+                    if k == $i {
+                        self.$i.mutate(&gene.$i)
+                    } else {
+                        gene.$i.clone()
+                    },
+                )+)
+            }
+
+            fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+                ($(self.$i.breed(&gene1.$i, &gene2.$i),)+)
+            }
+
+            fn random(&self) -> Self::Genome {
+                ($(self.$i.random(),)+)
+            }
+
+            fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+                0.0 $(+ self.$i.distance(&gene1.$i, &gene2.$i))+
+            }
+
+            fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+                true $(&& self.$i.is_same(&gene1.$i, &gene2.$i))+
+            }
+
+            fn size(&self, gene: &Self::Genome) -> usize {
+                0 $(+ self.$i.size(&gene.$i))+
+            }
+        }
+    };
+}
+
+tuple_breeder!(A 0);
+tuple_breeder!(A 0, B 1);
+tuple_breeder!(A 0, B 1, C 2);
+tuple_breeder!(A 0, B 1, C 2, D 3);
+tuple_breeder!(A 0, B 1, C 2, D 3, E 4);
+tuple_breeder!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breeder::{BoolBreeder, FloatBreeder, IntBreeder};
+
+    #[test]
+    fn option() {
+        let b = OptionBreeder {
+            toggle_rate: 1.0,
+            ..OptionBreeder::new(FloatBreeder::default())
+        };
+        assert!(b.mutate(&None).is_some());
+        assert!(b.mutate(&Some(0.5)).is_none());
+        assert!(!b.is_same(&None, &Some(0.5)));
+    }
+
+    #[test]
+    fn array() {
+        let b: ArrayBreeder<IntBreeder, 3> = ArrayBreeder::new(IntBreeder::default());
+        let g = b.breed(&b.random(), &b.mutate(&b.random()));
+        assert_eq!(g.len(), 3);
+        assert!(b.is_same(&g, &g));
+    }
+
+    #[test]
+    fn var_vec() {
+        let b = VarVecBreeder {
+            max_len: 4,
+            insert_rate: 1.0,
+            delete_rate: 0.0,
+            ..VarVecBreeder::new(FloatBreeder::default())
+        };
+        let mut g = b.random();
+        for _ in 0..10 {
+            g = b.mutate(&g);
+        }
+        assert_eq!(g.len(), 4);
+
+        let child = b.breed(&g, &vec![0.0]);
+        assert!(child.len() == 1 || child.len() == 4);
+    }
+
+    #[test]
+    fn tuple() {
+        let b = (FloatBreeder::default(), BoolBreeder::default(), IntBreeder::default());
+        let g = b.random();
+        let m = b.mutate(&g);
+        let changed = (g.0 != m.0) as usize + (g.1 != m.1) as usize + (g.2 != m.2) as usize;
+        assert!(changed <= 1);
+        assert!(b.is_same(&g, &g));
+    }
+}
@@ -0,0 +1,522 @@
+use crate::breeder::{Breeder, VecBreeder};
+use crate::utils::*;
+
+//////////////////////////////////
+/// Self Adaptive Breeder
+/// Breeds lists of floats that carry their own mutation step sizes
+pub struct SelfAdaptiveBreeder {
+    pub size: usize,
+    pub min: f64,
+    pub max: f64,
+    pub sigma: f64,
+    pub sigma_min: f64,
+    pub sigma_max: f64,
+    pub is_same_threshold: f64,
+}
+
+/// Values with one step size per value
+#[derive(Clone, Debug)]
+pub struct SaGenome {
+    pub values: Vec<f64>,
+    pub sigmas: Vec<f64>,
+}
+
+impl From<SaGenome> for Vec<f64> {
+    fn from(g: SaGenome) -> Self {
+        g.values
+    }
+}
+
+impl Default for SelfAdaptiveBreeder {
+    fn default() -> Self {
+        Self {
+            size: 100,
+            min: 0.0,
+            max: 1.0,
+            sigma: 0.1,
+            sigma_min: 1e-5,
+            sigma_max: 1.0,
+            is_same_threshold: 0.5,
+        }
+    }
+}
+
+impl Breeder for SelfAdaptiveBreeder {
+    type Genome = SaGenome;
+
+    fn mutate(&self, gene: &Self::Genome) -> Self::Genome {
+        let n = self.size as f64;
+        let tau_global = 1.0 / (2.0 * n).sqrt();
+        let tau_local = 1.0 / (2.0 * n.sqrt()).sqrt();
+        let global = tau_global * random_normal();
+
+        let sigmas: Vec<f64> = gene
+            .sigmas
+            .iter()
+            .map(|s| {
+                clamp(
+                    s * (global + tau_local * random_normal()).exp(),
+                    self.sigma_min,
+                    self.sigma_max,
+                )
+            })
+            .collect();
+
+        let values = gene
+            .values
+            .iter()
+            .zip(&sigmas)
+            .map(|(x, s)| clamp(x + s * random_normal(), self.min, self.max))
+            .collect();
+
+        SaGenome { values, sigmas }
+    }
+
+    fn breed(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> Self::Genome {
+        let values = gene1
+            .values
+            .iter()
+            .zip(&gene2.values)
+            .map(|(x1, x2)| if random() < 0.5 { *x1 } else { *x2 })
+            .collect();
+
+        // Intermediate recombination of step sizes
+        let sigmas = gene1
+            .sigmas
+            .iter()
+            .zip(&gene2.sigmas)
+            .map(|(s1, s2)| (s1 + s2) / 2.0)
+            .collect();
+
+        SaGenome { values, sigmas }
+    }
+
+    fn random(&self) -> Self::Genome {
+        SaGenome {
+            values: (0..self.size)
+                .map(|_| random_clamp(self.min, self.max))
+                .collect(),
+            sigmas: vec![self.sigma; self.size],
+        }
+    }
+
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
+        gene1.values.iter().zip(&gene2.values).fold(0f64, |s, (x, y)| {
+            s + (x - y) * (x - y) / (self.max - self.min) / (self.max - self.min)
+        }) / (self.size as f64)
+    }
+
+    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+        self.distance(gene1, gene2) < self.is_same_threshold
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.values.len()
+    }
+}
+
+///
+/// (1+1) Evolution Strategy
+/// Single parent with step size adapted by the 1/5th success rule
+///
+pub struct OnePlusOne {
+    /// Size and bounds of the genome
+    pub breeder: VecBreeder,
+
+    /// Mutation step size
+    pub sigma: f64,
+
+    /// Current parent and its score
+    pub parent: Option<(Vec<f64>, f64)>,
+
+    /// Stats
+    pub reported: usize,
+    pub successes: usize,
+}
+
+impl Iterator for OnePlusOne {
+    type Item = (i32, Vec<f64>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next())
+    }
+}
+
+impl OnePlusOne {
+    pub fn new(breeder: VecBreeder, sigma: f64) -> Self {
+        Self {
+            breeder,
+            sigma,
+            parent: None,
+            reported: 0,
+            successes: 0,
+        }
+    }
+
+    fn next<F>(&mut self) -> (i32, F)
+    where
+        F: From<Vec<f64>>,
+    {
+        let next = match &self.parent {
+            None => self.breeder.random(),
+            Some((p, _)) => p
+                .iter()
+                .map(|x| {
+                    clamp(
+                        x + self.sigma * random_normal(),
+                        self.breeder.min,
+                        self.breeder.max,
+                    )
+                })
+                .collect(),
+        };
+        (0, next.into())
+    }
+
+    pub fn report<F>(&mut self, _species_id: i32, genome: F, score: f64) -> bool
+    where
+        F: Into<Vec<f64>>,
+    {
+        self.reported += 1;
+        let success = match &self.parent {
+            Some((_, s)) => score >= *s,
+            None => true,
+        };
+
+        // Keep the success rate around 1/5
+        let d = (self.breeder.size as f64 + 1.0).sqrt();
+        let s = if success { 1.0 } else { 0.0 };
+        self.sigma *= ((s - 0.2) / d).exp();
+
+        if success {
+            self.successes += 1;
+            self.parent = Some((genome.into(), score));
+        }
+        success
+    }
+}
+
+///
+/// Covariance Matrix Adaptation Evolution Strategy
+/// Samples generations of lambda genomes around a mean and adapts
+/// a full covariance matrix from the best mu of them
+///
+pub struct CmaEs {
+    /// Size and bounds of the genome
+    pub breeder: VecBreeder,
+
+    /// Global step size
+    pub sigma: f64,
+
+    /// Distribution mean
+    pub mean: Vec<f64>,
+
+    /// Population and parent sizes
+    pub lambda: usize,
+    pub mu: usize,
+
+    /// Covariance, its cholesky factor, and evolution paths
+    cov: Vec<Vec<f64>>,
+    chol: Vec<Vec<f64>>,
+    pc: Vec<f64>,
+    ps: Vec<f64>,
+
+    /// Recombination weights and learning rates
+    weights: Vec<f64>,
+    mueff: f64,
+    cc: f64,
+    cs: f64,
+    c1: f64,
+    cmu: f64,
+    damps: f64,
+    chi_n: f64,
+
+    /// Reports of the current generation
+    reported: Vec<(Vec<f64>, f64)>,
+
+    /// Stats
+    pub generations: i32,
+    pub champion: Option<(f64, Vec<f64>)>,
+}
+
+impl Iterator for CmaEs {
+    type Item = (i32, Vec<f64>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next())
+    }
+}
+
+impl CmaEs {
+    /// New CMA-ES starting from a random mean
+    pub fn new(breeder: VecBreeder, sigma: f64) -> Self {
+        let mean = breeder.random();
+        Self::from_mean(breeder, mean, sigma)
+    }
+
+    /// New CMA-ES starting from a given mean
+    pub fn from_mean(breeder: VecBreeder, mean: Vec<f64>, sigma: f64) -> Self {
+        let n = breeder.size as f64;
+        let lambda = 4 + (3.0 * n.ln()).floor() as usize;
+        let mu = lambda / 2;
+
+        let weights: Vec<f64> = (1..=mu)
+            .map(|i| (mu as f64 + 0.5).ln() - (i as f64).ln())
+            .collect();
+        let total: f64 = weights.iter().sum();
+        let weights: Vec<f64> = weights.iter().map(|w| w / total).collect();
+        let mueff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let cc = (4.0 + mueff / n) / (n + 4.0 + 2.0 * mueff / n);
+        let cs = (mueff + 2.0) / (n + mueff + 5.0);
+        let c1 = 2.0 / ((n + 1.3).powi(2) + mueff);
+        let cmu = (1.0 - c1).min(2.0 * (mueff - 2.0 + 1.0 / mueff) / ((n + 2.0).powi(2) + mueff));
+        let damps = 1.0 + 2.0 * (((mueff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + cs;
+        let chi_n = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+
+        let size = breeder.size;
+        Self {
+            breeder,
+            sigma,
+            mean,
+            lambda,
+            mu,
+            cov: identity(size),
+            chol: identity(size),
+            pc: vec![0.0; size],
+            ps: vec![0.0; size],
+            weights,
+            mueff,
+            cc,
+            cs,
+            c1,
+            cmu,
+            damps,
+            chi_n,
+            reported: vec![],
+            generations: 0,
+            champion: None,
+        }
+    }
+
+    fn next<F>(&mut self) -> (i32, F)
+    where
+        F: From<Vec<f64>>,
+    {
+        let z: Vec<f64> = (0..self.breeder.size).map(|_| random_normal()).collect();
+        let y = mat_vec(&self.chol, &z);
+        let next = self
+            .mean
+            .iter()
+            .zip(y)
+            .map(|(m, y)| clamp(m + self.sigma * y, self.breeder.min, self.breeder.max))
+            .collect::<Vec<f64>>();
+        (self.generations, next.into())
+    }
+
+    pub fn report<F>(&mut self, _species_id: i32, genome: F, score: f64) -> bool
+    where
+        F: Into<Vec<f64>>,
+    {
+        let genome = genome.into();
+        match &self.champion {
+            Some((s, _)) if *s >= score => (),
+            _ => self.champion = Some((score, genome.clone())),
+        }
+
+        self.reported.push((genome, score));
+        if self.reported.len() >= self.lambda {
+            self.update();
+            return true;
+        }
+        false
+    }
+
+    /// Update the distribution from a full generation
+    fn update(&mut self) {
+        let n = self.breeder.size;
+        self.reported
+            .sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        // Steps of the selected genomes
+        let ys: Vec<Vec<f64>> = self.reported[..self.mu]
+            .iter()
+            .map(|(x, _)| {
+                x.iter()
+                    .zip(&self.mean)
+                    .map(|(x, m)| (x - m) / self.sigma)
+                    .collect()
+            })
+            .collect();
+        self.reported.clear();
+
+        let mut yw = vec![0.0; n];
+        for (w, y) in self.weights.iter().zip(&ys) {
+            for (yw, y) in yw.iter_mut().zip(y) {
+                *yw += w * y;
+            }
+        }
+
+        for (m, y) in self.mean.iter_mut().zip(&yw) {
+            *m += self.sigma * y;
+        }
+
+        // Step size path
+        let zw = solve_lower(&self.chol, &yw);
+        let a = (self.cs * (2.0 - self.cs) * self.mueff).sqrt();
+        for (p, z) in self.ps.iter_mut().zip(&zw) {
+            *p = (1.0 - self.cs) * *p + a * z;
+        }
+        let ps_norm = self.ps.iter().map(|p| p * p).sum::<f64>().sqrt();
+
+        self.generations += 1;
+        let decay = 1.0 - (1.0 - self.cs).powi(2 * self.generations);
+        let hsig = ps_norm / decay.sqrt() / self.chi_n < 1.4 + 2.0 / (n as f64 + 1.0);
+        let hsig = if hsig { 1.0 } else { 0.0 };
+
+        // Covariance path
+        let a = (self.cc * (2.0 - self.cc) * self.mueff).sqrt();
+        for (p, y) in self.pc.iter_mut().zip(&yw) {
+            *p = (1.0 - self.cc) * *p + hsig * a * y;
+        }
+
+        // Covariance update
+        let old = (1.0 - self.c1 - self.cmu)
+            + self.c1 * (1.0 - hsig) * self.cc * (2.0 - self.cc);
+        for i in 0..n {
+            for j in 0..n {
+                let rank_mu = self
+                    .weights
+                    .iter()
+                    .zip(&ys)
+                    .fold(0.0, |s, (w, y)| s + w * y[i] * y[j]);
+                self.cov[i][j] = old * self.cov[i][j]
+                    + self.c1 * self.pc[i] * self.pc[j]
+                    + self.cmu * rank_mu;
+            }
+        }
+
+        self.sigma *= ((self.cs / self.damps) * (ps_norm / self.chi_n - 1.0)).exp();
+
+        match cholesky(&self.cov) {
+            Some(chol) => self.chol = chol,
+            None => {
+                // Numerical trouble, restart the shape
+                self.cov = identity(n);
+                self.chol = identity(n);
+                self.pc = vec![0.0; n];
+                self.ps = vec![0.0; n];
+            }
+        }
+    }
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect()
+}
+
+fn mat_vec(m: &[Vec<f64>], v: &[f64]) -> Vec<f64> {
+    m.iter()
+        .map(|row| row.iter().zip(v).map(|(a, b)| a * b).sum())
+        .collect()
+}
+
+/// Lower triangular L with L * L^T = m
+fn cholesky(m: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = m.len();
+    let mut l = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let s: f64 = (0..j).map(|k| l[i][k] * l[j][k]).sum();
+            if i == j {
+                let d = m[i][i] - s;
+                if d <= 0.0 || !d.is_finite() {
+                    return None;
+                }
+                l[i][j] = d.sqrt();
+            } else {
+                l[i][j] = (m[i][j] - s) / l[j][j];
+            }
+        }
+    }
+    Some(l)
+}
+
+/// Solve L * x = v for lower triangular L
+fn solve_lower(l: &[Vec<f64>], v: &[f64]) -> Vec<f64> {
+    let mut x = vec![0.0; v.len()];
+    for i in 0..v.len() {
+        let s: f64 = (0..i).map(|k| l[i][k] * x[k]).sum();
+        x[i] = (v[i] - s) / l[i][i];
+    }
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{assert_delta, assert_delta_vector};
+
+    fn sphere(x: &[f64]) -> f64 {
+        -x.iter().map(|x| (x - 0.3).powi(2)).sum::<f64>()
+    }
+
+    fn breeder() -> VecBreeder {
+        VecBreeder {
+            size: 5,
+            min: -1.0,
+            max: 1.0,
+            ..VecBreeder::default()
+        }
+    }
+
+    #[test]
+    fn test_cholesky() {
+        let m = vec![vec![4.0, 2.0], vec![2.0, 3.0]];
+        let l = cholesky(&m).unwrap();
+        assert_delta!(l[0][0] * l[0][0], 4.0, 1e-9);
+        assert_delta!(l[1][0] * l[0][0], 2.0, 1e-9);
+        assert_delta!(l[1][0] * l[1][0] + l[1][1] * l[1][1], 3.0, 1e-9);
+
+        let x = solve_lower(&l, &[2.0, 1.0]);
+        assert_delta_vector!(mat_vec(&l, &x), vec![2.0, 1.0], 1e-9);
+    }
+
+    #[test]
+    fn test_one_plus_one() {
+        let mut es = OnePlusOne::new(breeder(), 0.3);
+        for _ in 0..2000 {
+            let (_, x): (_, Vec<f64>) = es.next();
+            let score = sphere(&x);
+            es.report(0, x, score);
+        }
+        assert!(es.parent.unwrap().1 > -1e-3);
+    }
+
+    #[test]
+    fn test_cma_es() {
+        let mut es = CmaEs::new(breeder(), 0.3);
+        for _ in 0..3000 {
+            let (_, x): (_, Vec<f64>) = es.next();
+            let score = sphere(&x);
+            es.report(0, x, score);
+        }
+        assert!(es.champion.unwrap().0 > -1e-6);
+        assert_delta_vector!(es.mean, vec![0.3; 5], 1e-2);
+    }
+
+    #[test]
+    fn test_self_adaptive() {
+        let b = SelfAdaptiveBreeder {
+            size: 5,
+            ..SelfAdaptiveBreeder::default()
+        };
+        let g = b.random();
+        let m = b.mutate(&g);
+        assert_eq!(m.values.len(), 5);
+        assert!(m.sigmas.iter().zip(&g.sigmas).any(|(a, b)| a != b));
+        assert!(m.values.iter().all(|x| *x >= 0.0 && *x <= 1.0));
+    }
+}
@@ -0,0 +1,27 @@
+use crate::breeder::Breeder;
+use crate::pool::Operator;
+
+//////////////////////////////////
+/// Evolver
+/// Issues genomes and learns from the fitness reported for them,
+/// so a simulation can swap a pool for another search strategy
+pub trait Evolver {
+    type Breeder: Breeder;
+
+    /// Breeder of the genomes
+    fn breeder(&self) -> &Self::Breeder;
+
+    /// Next genome to evaluate with its id
+    fn next_genome(&mut self) -> (i32, <Self::Breeder as Breeder>::Genome);
+
+    /// Report the fitness of an evaluated genome
+    fn report_fitness(
+        &mut self,
+        id: i32,
+        genome: <Self::Breeder as Breeder>::Genome,
+        fitness: f64,
+    );
+
+    /// Id for a genome bred outside the evolver from parents
+    fn adopt(&mut self, parents: Vec<i32>, operator: Operator) -> i32;
+}
@@ -0,0 +1,227 @@
+use crate::breeder::Breeder;
+use crate::evolver::Evolver;
+use crate::pool::{Operator, Pool};
+use crate::utils::*;
+
+/// Which islands receive an island's migrants
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Topology {
+    /// The next island
+    Ring,
+    /// Every other island
+    FullyConnected,
+    /// This many other islands picked at each migration
+    Random(usize),
+}
+
+impl Topology {
+    /// Islands receiving migrants from island i of n
+    pub fn targets(&self, i: usize, n: usize) -> Vec<usize> {
+        if n < 2 {
+            return vec![];
+        }
+        match *self {
+            Topology::Ring => vec![(i + 1) % n],
+            Topology::FullyConnected => (0..n).filter(|j| *j != i).collect(),
+            Topology::Random(k) => {
+                let mut others: Vec<_> = (0..n).filter(|j| *j != i).collect();
+                others.shuffle();
+                others.truncate(k);
+                others
+            }
+        }
+    }
+}
+
+///
+/// Island model
+/// Independent pools that send their best genomes to each other
+///
+pub struct Islands<B>
+where
+    B: Breeder,
+{
+    pub islands: Vec<Pool<B>>,
+
+    pub topology: Topology,
+
+    /// Generations between migrations of an island
+    pub interval: i32,
+
+    /// Genomes sent to every target
+    pub migrants: usize,
+
+    /// Generation of each island's last migration
+    migrated: Vec<i32>,
+
+    /// Island issuing the next genome through the Evolver trait
+    turn: usize,
+}
+
+/// Genomes are issued by the islands in turn,
+/// their ids tell the island apart as id * islands + island
+impl<B> Evolver for Islands<B>
+where
+    B: Breeder,
+{
+    type Breeder = B;
+
+    fn breeder(&self) -> &B {
+        self.islands[0].breeder()
+    }
+
+    fn next_genome(&mut self) -> (i32, B::Genome) {
+        let island = self.turn % self.islands.len();
+        self.turn = island + 1;
+        let (id, genome) = self.next(island);
+        (self.global_id(island, id), genome)
+    }
+
+    fn report_fitness(&mut self, id: i32, genome: B::Genome, fitness: f64) {
+        let (island, id) = self.local_id(id);
+        self.report(island, id, genome, fitness);
+    }
+
+    fn adopt(&mut self, parents: Vec<i32>, operator: Operator) -> i32 {
+        // Born on the island of the first parent
+        let island = match parents.first() {
+            Some(p) => self.local_id(*p).0,
+            None => {
+                self.turn = (self.turn + 1) % self.islands.len();
+                self.turn
+            }
+        };
+        let parents = parents
+            .into_iter()
+            .map(|p| self.local_id(p))
+            .filter(|p| p.0 == island)
+            .map(|p| p.1)
+            .collect();
+        let id = self.islands[island].adopt(parents, operator);
+        self.global_id(island, id)
+    }
+}
+
+impl<B> Islands<B>
+where
+    B: Breeder,
+{
+    pub fn new(islands: Vec<Pool<B>>, topology: Topology) -> Self {
+        let migrated = vec![0; islands.len()];
+        Self {
+            islands,
+            topology,
+            interval: 5,
+            migrants: 2,
+            migrated,
+            turn: 0,
+        }
+    }
+
+    fn global_id(&self, island: usize, id: i32) -> i32 {
+        id * self.islands.len() as i32 + island as i32
+    }
+
+    /// Island and id within it of a global id
+    fn local_id(&self, id: i32) -> (usize, i32) {
+        let n = self.islands.len() as i32;
+        (id.rem_euclid(n) as usize, id.div_euclid(n))
+    }
+
+    /// Next genome of an island
+    pub fn next<F>(&mut self, island: usize) -> (i32, F)
+    where
+        F: From<B::Genome>,
+    {
+        let (id, genome) = self.islands[island].next().unwrap();
+        (id, genome.into())
+    }
+
+    /// Report to an island, migrating its best genomes when due
+    pub fn report<F>(&mut self, island: usize, id: i32, genome: F, score: f64) -> bool
+    where
+        F: Into<B::Genome>,
+    {
+        let res = self.islands[island].report(id, genome, score);
+
+        let generation = self.islands[island].generations;
+        if generation - self.migrated[island] >= self.interval {
+            self.migrated[island] = generation;
+            self.migrate(island);
+        }
+        res
+    }
+
+    /// Send copies of an island's best genomes to its targets
+    pub fn migrate(&mut self, island: usize) {
+        let best = self.islands[island].best(self.migrants);
+        for target in self.topology.targets(island, self.islands.len()) {
+            for (genome, score) in best.iter() {
+                self.islands[target].immigrate(genome.clone(), *score);
+            }
+        }
+    }
+
+    /// Best genome over all islands
+    pub fn champion(&self) -> Option<&(f64, B::Genome)> {
+        self.islands
+            .iter()
+            .filter_map(|p| p.champion.as_ref())
+            .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::breeder::FloatBreeder;
+
+    #[test]
+    fn test_topology() {
+        assert_eq!(Topology::Ring.targets(2, 3), vec![0]);
+        assert_eq!(Topology::FullyConnected.targets(1, 3), vec![0, 2]);
+        let random = Topology::Random(2).targets(0, 5);
+        assert_eq!(random.len(), 2);
+        assert!(!random.contains(&0));
+        assert!(Topology::Ring.targets(0, 1).is_empty());
+    }
+
+    #[test]
+    fn test_migration() {
+        let pools = (0..3).map(|_| Pool::new(10, FloatBreeder::default())).collect();
+        let mut islands = Islands::new(pools, Topology::Ring);
+        islands.interval = 1;
+        islands.migrants = 1;
+
+        // Only the first island finds good genomes
+        for _ in 0..10 {
+            let (id, f): (_, f64) = islands.next(0);
+            islands.report(0, id, f, 100.0 + f);
+        }
+
+        let best = islands.islands[1].best(1);
+        assert_eq!(best.len(), 1);
+        assert!(best[0].1 >= 99.0);
+        assert!(islands.islands[2].best(1).is_empty());
+        assert!(islands.champion().unwrap().0 >= 99.0);
+    }
+
+    #[test]
+    fn test_evolver() {
+        let pools = (0..3).map(|_| Pool::new(10, FloatBreeder::default())).collect();
+        let mut islands = Islands::new(pools, Topology::Ring);
+
+        // Islands issue in turn and reports go back to the issuing island
+        let issued: Vec<_> = (0..6).map(|_| islands.next_genome()).collect();
+        for (id, f) in issued.iter() {
+            islands.report_fitness(*id, *f, 1.0);
+        }
+        assert!(islands.islands.iter().all(|p| p.best(10).len() == 2));
+
+        // Children are born on their first parent's island
+        let child = islands.adopt(vec![issued[1].0], Operator::Mutate);
+        let (island, id) = islands.local_id(child);
+        assert_eq!(island, 1);
+        assert_eq!(islands.islands[1].lineage.get(id).unwrap().parents.len(), 1);
+    }
+}
@@ -1,6 +1,12 @@
 pub mod breeder;
+pub mod es;
+pub mod evolver;
+pub mod islands;
+pub mod lineage;
+pub mod map_elites;
 pub mod neat;
 pub mod pool;
+pub mod stats;
 pub mod utils;
 
 pub use crate::breeder::*;
@@ -8,4 +14,9 @@ pub use crate::neat::NeatBreeder;
 pub use crate::neat::NeatGenome;
 pub use crate::neat::NeatNetwork;
 pub use crate::pool::Pool;
-pub use evo_macros::derive_breeder;
+pub use crate::evolver::Evolver;
+pub use crate::islands::Islands;
+pub use crate::lineage::Lineage;
+pub use crate::map_elites::MapElites;
+pub use crate::es::{CmaEs, OnePlusOne, SelfAdaptiveBreeder};
+pub use evo_macros::{derive_breeder, Breeder};
@@ -0,0 +1,268 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::pool::Operator;
+
+//////////////////////////////////
+/// Record
+/// Birth of a single genome
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub id: i32,
+    /// Parents, the better parent first
+    pub parents: Vec<i32>,
+    pub operator: Operator,
+    /// Pool generation the genome was issued in
+    pub generation: i32,
+    /// Score once the genome is reported
+    pub score: Option<f64>,
+}
+
+///
+/// Lineage log
+/// Record of the genomes issued by a pool, in order of birth
+///
+#[derive(Debug, Clone, Default)]
+pub struct Lineage {
+    records: Vec<Record>,
+    index: HashMap<i32, usize>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the birth of a genome
+    pub fn push(&mut self, id: i32, parents: Vec<i32>, operator: Operator, generation: i32) {
+        self.index.insert(id, self.records.len());
+        self.records.push(Record {
+            id,
+            parents,
+            operator,
+            generation,
+            score: None,
+        });
+    }
+
+    /// Set the score of a genome, a score is only recorded once
+    pub fn score(&mut self, id: i32, score: f64) {
+        if let Some(&i) = self.index.get(&id) {
+            self.records[i].score.get_or_insert(score);
+        }
+    }
+
+    pub fn get(&self, id: i32) -> Option<&Record> {
+        self.index.get(&id).map(|&i| &self.records[i])
+    }
+
+    /// All records in order of birth
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Drop records with no living descendants
+    /// The best scored genome and its ancestors are always kept
+    pub fn prune(&mut self, living: impl IntoIterator<Item = i32>) {
+        let mut keep = HashSet::new();
+        let mut stack: Vec<i32> = living.into_iter().collect();
+        stack.extend(self.best().map(|r| r.id));
+        while let Some(id) = stack.pop() {
+            if !keep.insert(id) {
+                continue;
+            }
+            if let Some(r) = self.get(id) {
+                stack.extend(r.parents.iter());
+            }
+        }
+
+        self.records.retain(|r| keep.contains(&r.id));
+        self.index = self
+            .records
+            .iter()
+            .enumerate()
+            .map(|(i, r)| (r.id, i))
+            .collect();
+    }
+
+    /// Best scored genome
+    pub fn best(&self) -> Option<&Record> {
+        self.records
+            .iter()
+            .filter(|r| r.score.is_some_and(|s| s.is_finite()))
+            .max_by(|a, b| a.score.partial_cmp(&b.score).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// A genome and all its known ancestors, each once, youngest first
+    pub fn ancestry(&self, id: i32) -> Vec<&Record> {
+        let mut seen = HashSet::new();
+        let mut stack = vec![id];
+        let mut out = vec![];
+        while let Some(id) = stack.pop() {
+            if !seen.insert(id) {
+                continue;
+            }
+            if let Some(r) = self.get(id) {
+                stack.extend(r.parents.iter().rev());
+                out.push(r);
+            }
+        }
+        out.sort_by_key(|r| std::cmp::Reverse(r.id));
+        out
+    }
+
+    /// Ancestry of a genome as a Graphviz digraph, edges point from parent to child
+    pub fn to_dot(&self, id: i32) -> String {
+        let ancestry = self.ancestry(id);
+        let mut dot = String::from("digraph lineage {\n");
+        for r in ancestry.iter() {
+            let score = r.score.map(|s| format!("{:.3}", s)).unwrap_or_default();
+            writeln!(
+                dot,
+                "  {} [label=\"{}\\n{:?} g{}\\n{}\"];",
+                r.id, r.id, r.operator, r.generation, score
+            )
+            .unwrap();
+        }
+        for r in ancestry.iter() {
+            for p in r.parents.iter().filter(|p| self.index.contains_key(p)) {
+                writeln!(dot, "  {} -> {};", p, r.id).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Ancestry of a genome as a nested JSON tree
+    /// Ancestors reached twice are only expanded the first time
+    pub fn to_json(&self, id: i32) -> String {
+        // Walk with an explicit stack, ancestries can be thousands of generations deep
+        enum Step {
+            Node(i32),
+            Text(&'static str),
+        }
+
+        let mut json = String::new();
+        let mut seen = HashSet::new();
+        let mut stack = vec![Step::Node(id)];
+        while let Some(step) = stack.pop() {
+            let id = match step {
+                Step::Node(id) => id,
+                Step::Text(t) => {
+                    json.push_str(t);
+                    continue;
+                }
+            };
+
+            let r = match self.get(id) {
+                Some(r) if seen.insert(id) => r,
+                _ => {
+                    write!(json, "{{\"id\":{}}}", id).unwrap();
+                    continue;
+                }
+            };
+
+            let score = r
+                .score
+                .filter(|s| s.is_finite())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| "null".into());
+            write!(
+                json,
+                "{{\"id\":{},\"operator\":\"{:?}\",\"generation\":{},\"score\":{},\"parents\":[",
+                r.id, r.operator, r.generation, score
+            )
+            .unwrap();
+
+            // Parents come off the stack in order, separated by commas
+            stack.push(Step::Text("]}"));
+            for (i, p) in r.parents.iter().enumerate().rev() {
+                stack.push(Step::Node(*p));
+                if i > 0 {
+                    stack.push(Step::Text(","));
+                }
+            }
+        }
+        json
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lineage() -> Lineage {
+        let mut l = Lineage::new();
+        l.push(1, vec![], Operator::Random, 0);
+        l.push(2, vec![], Operator::Random, 0);
+        l.push(3, vec![1], Operator::Mutate, 1);
+        l.push(4, vec![3, 1], Operator::Cross, 2);
+        l.score(4, 2.5);
+        l
+    }
+
+    #[test]
+    fn test_ancestry() {
+        let l = lineage();
+        let ids: Vec<_> = l.ancestry(4).iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![4, 3, 1]);
+        assert_eq!(l.get(4).unwrap().score, Some(2.5));
+        assert_eq!(l.best().unwrap().id, 4);
+        assert!(Lineage::new().best().is_none());
+    }
+
+    #[test]
+    fn test_export() {
+        let l = lineage();
+        let dot = l.to_dot(4);
+        assert!(dot.contains("3 -> 4;"));
+        assert!(dot.contains("1 -> 3;"));
+        assert!(!dot.contains("2 ["));
+
+        assert_eq!(
+            l.to_json(3),
+            "{\"id\":3,\"operator\":\"Mutate\",\"generation\":1,\"score\":null,\"parents\":[\
+             {\"id\":1,\"operator\":\"Random\",\"generation\":0,\"score\":null,\"parents\":[]}]}"
+        );
+        assert!(l.to_json(4).ends_with("{\"id\":1}]}"));
+    }
+
+    #[test]
+    fn test_prune() {
+        let mut l = lineage();
+        l.push(5, vec![2], Operator::Mutate, 1);
+        l.push(6, vec![5], Operator::Mutate, 2);
+
+        // 4 is kept as the best, 6 is alive, 2 and 5 are its ancestors
+        l.prune(vec![6]);
+        assert_eq!(l.len(), 6);
+
+        l.push(7, vec![3], Operator::Mutate, 3);
+        l.prune(vec![7]);
+        let ids: Vec<_> = l.records().iter().map(|r| r.id).collect();
+        assert_eq!(ids, vec![1, 3, 4, 7]);
+        assert_eq!(l.get(7).unwrap().parents, vec![3]);
+        assert!(l.get(5).is_none());
+    }
+
+    #[test]
+    fn test_deep_export() {
+        let mut l = Lineage::new();
+        l.push(0, vec![], Operator::Random, 0);
+        for id in 1..100_000 {
+            l.push(id, vec![id - 1], Operator::Mutate, id);
+        }
+        let json = l.to_json(99_999);
+        assert!(json.starts_with("{\"id\":99999,"));
+        assert_eq!(json.matches("]}").count(), 100_000);
+        assert_eq!(l.to_dot(99_999).matches("->").count(), 99_999);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::breeder::Breeder;
+use crate::evolver::Evolver;
+use crate::pool::{Operator, Ratios};
+use crate::utils::*;
+
+/// Maps a genome onto its behaviour descriptor
+pub type Descriptor<G> = Box<dyn Fn(&G) -> Vec<f64> + Send + Sync>;
+
+//////////////////////////////////
+/// Axis
+/// One dimension of the behaviour grid
+#[derive(Debug, Clone, Copy)]
+pub struct Axis {
+    pub min: f64,
+    pub max: f64,
+    pub bins: usize,
+}
+
+impl Axis {
+    pub fn new(min: f64, max: f64, bins: usize) -> Self {
+        Self { min, max, bins }
+    }
+
+    /// Bin index of a value, values outside the range go to the edges
+    pub fn bin(&self, x: f64) -> usize {
+        if self.bins == 0 {
+            return 0;
+        }
+        let f = (x - self.min) / (self.max - self.min);
+        clamp((f * self.bins as f64).floor(), 0.0, (self.bins - 1) as f64) as usize
+    }
+}
+
+///
+/// MAP-Elites archive
+/// Keeps the best genome found for every cell of a behaviour grid
+///
+pub struct MapElites<B>
+where
+    B: Breeder,
+{
+    /// Breeder instance
+    breeder: B,
+
+    /// Behaviour grid
+    axes: Vec<Axis>,
+
+    /// Describes a genome when no behaviour is reported
+    descriptor: Descriptor<B::Genome>,
+
+    /// Flattened grid of elites
+    cells: Vec<Option<(B::Genome, f64)>>,
+
+    /// Indices of occupied cells
+    filled: Vec<usize>,
+
+    /// Ratios of different methods
+    pub ratios: Ratios<f64>,
+
+    /// Number of reports before breeding starts
+    pub initial: usize,
+
+    /// Stats
+    pub reported: usize,
+    pub champion: Option<(f64, B::Genome)>,
+}
+
+impl<B> Iterator for MapElites<B>
+where
+    B: Breeder,
+{
+    type Item = (i32, B::Genome);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next())
+    }
+}
+
+/// Genomes are not tracked by id, elites are kept by behaviour
+impl<B> Evolver for MapElites<B>
+where
+    B: Breeder,
+{
+    type Breeder = B;
+
+    fn breeder(&self) -> &B {
+        &self.breeder
+    }
+
+    fn next_genome(&mut self) -> (i32, B::Genome) {
+        self.next()
+    }
+
+    fn report_fitness(&mut self, id: i32, genome: B::Genome, fitness: f64) {
+        self.report(id, genome, fitness);
+    }
+
+    fn adopt(&mut self, _parents: Vec<i32>, _operator: Operator) -> i32 {
+        0
+    }
+}
+
+impl<B> MapElites<B>
+where
+    B: Breeder,
+{
+    /// New archive over a grid with a breeder and behaviour descriptor
+    pub fn new<D>(axes: Vec<Axis>, breeder: B, descriptor: D) -> Self
+    where
+        D: Fn(&B::Genome) -> Vec<f64> + Send + Sync + 'static,
+    {
+        let size = axes.iter().map(|a| a.bins.max(1)).product();
+        Self {
+            breeder,
+            axes,
+            descriptor: Box::new(descriptor),
+            cells: (0..size).map(|_| None).collect(),
+            filled: vec![],
+            ratios: Ratios {
+                top: 0.0,
+                random: 0.05,
+                cross: 0.3,
+                mutate: 0.65,
+            },
+            initial: 10,
+            reported: 0,
+            champion: None,
+        }
+    }
+
+    fn next<F>(&mut self) -> (i32, F)
+    where
+        F: From<B::Genome>,
+    {
+        if self.reported < self.initial || self.filled.is_empty() {
+            return (0, self.breeder.random().into());
+        }
+
+        let cum = self.ratios.cumulative();
+        let next = match random() * cum.random {
+            x if x < cum.top => self.sample().0.clone(),
+            x if x < cum.mutate => self.breeder.mutate(&self.sample().0),
+            x if x < cum.cross => {
+                let g1 = self.sample();
+                let g2 = self.sample();
+
+                if g1.1 > g2.1 {
+                    self.breeder.breed(&g1.0, &g2.0)
+                } else {
+                    self.breeder.breed(&g2.0, &g1.0)
+                }
+            }
+            _ => self.breeder.random(),
+        };
+
+        (0, next.into())
+    }
+
+    /// Report a genome, describing its behaviour with the descriptor
+    /// Returns true if the genome became an elite
+    pub fn report<F>(&mut self, _species_id: i32, genome: F, score: f64) -> bool
+    where
+        F: Into<B::Genome>,
+    {
+        let genome = genome.into();
+        let behaviour = (self.descriptor)(&genome);
+        self.report_behaviour(&behaviour, genome, score)
+    }
+
+    /// Report a genome with a behaviour measured elsewhere
+    /// Returns true if the genome became an elite
+    pub fn report_behaviour<F>(&mut self, behaviour: &[f64], genome: F, score: f64) -> bool
+    where
+        F: Into<B::Genome>,
+    {
+        self.reported += 1;
+        let idx = self.index(behaviour);
+
+        match &self.cells[idx] {
+            Some((_, s)) if *s >= score => return false,
+            Some(_) => (),
+            None => self.filled.push(idx),
+        }
+
+        let genome = genome.into();
+        match &self.champion {
+            Some((s, _)) if *s >= score => (),
+            _ => self.champion = Some((score, genome.clone())),
+        }
+        self.cells[idx] = Some((genome, score));
+        true
+    }
+
+    /// Elite of the cell containing a behaviour
+    pub fn get(&self, behaviour: &[f64]) -> Option<&(B::Genome, f64)> {
+        self.cells[self.index(behaviour)].as_ref()
+    }
+
+    /// All elites with their cell coordinates
+    pub fn elites(&self) -> impl Iterator<Item = (Vec<usize>, &B::Genome, f64)> {
+        self.filled.iter().map(move |&i| {
+            let (g, s) = self.cells[i].as_ref().unwrap();
+            (self.coords(i), g, *s)
+        })
+    }
+
+    /// Number of occupied cells
+    pub fn len(&self) -> usize {
+        self.filled.len()
+    }
+
+    /// Is the archive empty
+    pub fn is_empty(&self) -> bool {
+        self.filled.is_empty()
+    }
+
+    /// Fraction of cells occupied
+    pub fn coverage(&self) -> f64 {
+        self.filled.len() as f64 / self.cells.len() as f64
+    }
+
+    fn sample(&self) -> &(B::Genome, f64) {
+        self.cells[*self.filled.sample()].as_ref().unwrap()
+    }
+
+    /// Flat cell index of a behaviour
+    fn index(&self, behaviour: &[f64]) -> usize {
+        self.axes.iter().enumerate().fold(0, |idx, (i, a)| {
+            idx * a.bins.max(1) + a.bin(*behaviour.get(i).unwrap_or(&a.min))
+        })
+    }
+
+    /// Cell coordinates of a flat index
+    fn coords(&self, mut idx: usize) -> Vec<usize> {
+        let mut coords: Vec<usize> = self
+            .axes
+            .iter()
+            .rev()
+            .map(|a| {
+                let c = idx % a.bins.max(1);
+                idx /= a.bins.max(1);
+                c
+            })
+            .collect();
+        coords.reverse();
+        coords
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::breeder::FloatBreeder;
+
+    fn elites() -> MapElites<FloatBreeder> {
+        MapElites::new(
+            vec![Axis::new(-1.0, 1.0, 4)],
+            FloatBreeder::default(),
+            |g: &f64| vec![*g],
+        )
+    }
+
+    #[test]
+    fn test_keeps_best_per_cell() {
+        let mut map = elites();
+        assert!(map.report(0, -0.9, 1.0));
+        assert!(!map.report(0, -0.8, 0.5));
+        assert!(map.report(0, -0.7, 2.0));
+        assert!(map.report(0, 0.9, 0.1));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&[-1.0]).unwrap().0, -0.7);
+        assert_eq!(map.champion.as_ref().unwrap().0, 2.0);
+    }
+
+    #[test]
+    fn test_coords() {
+        let map: MapElites<FloatBreeder> = MapElites::new(
+            vec![Axis::new(0.0, 1.0, 3), Axis::new(0.0, 1.0, 5)],
+            FloatBreeder::default(),
+            |g: &f64| vec![*g, *g],
+        );
+        let idx = map.index(&[0.5, 0.9]);
+        assert_eq!(map.coords(idx), vec![1, 4]);
+    }
+
+    #[test]
+    fn test_fill() {
+        let mut map = elites();
+        for _ in 0..1000 {
+            let (_, f): (_, f64) = map.next();
+            map.report(0, f, random());
+        }
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.coverage(), 1.0);
+    }
+}
@@ -96,9 +96,13 @@ impl Breeder for NeatBreeder {
         {0..5}.for_each(|_|g = self.mutate(&g));
         g
     }
-    fn is_same(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> bool {
+    fn distance(&self, gene1: &Self::Genome, gene2: &Self::Genome) -> f64 {
         // gene1.is_same_specie(gene2)
-        gene1.compatibility_distance(gene2) < 1.0f64
+        gene1.compatibility_distance(gene2)
+    }
+
+    fn size(&self, gene: &Self::Genome) -> usize {
+        gene.genes.len()
     }
 }
 
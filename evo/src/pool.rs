@@ -1,6 +1,10 @@
 use statrs::distribution::{Normal, ContinuousCDF};
+use std::collections::HashMap;
 
 use crate::breeder::Breeder;
+use crate::evolver::Evolver;
+use crate::lineage::Lineage;
+use crate::stats::{GenStats, StatsSink};
 use crate::utils::*;
 
 ///
@@ -24,11 +28,44 @@ where
     /// Ratios of different methods
     pub ratios: Ratios<f64>,
 
+    /// How ratios follow the success of each method
+    pub adaptation: Adaptation,
+
+    /// Running success rate of each method
+    pub quality: Ratios<f64>,
+
+    /// Issued genomes waiting for a report by id, with the generation they were issued in.
+    /// Genomes not reported by the end of the next generation are forgotten
+    pending: HashMap<i32, (Operator, Option<f64>, i32)>,
+    next_id: i32,
+
+    /// Parents and operator of every issued genome
+    pub lineage: Lineage,
+
+    /// Drop lineage records with no living descendants at the end of every generation,
+    /// keeps the lineage bounded in long runs
+    pub prune_lineage: bool,
+
+    /// Scores and operators reported in the current generation
+    gen_scores: Vec<f64>,
+    gen_operators: Ratios<usize>,
+    improved: bool,
+
+    /// Receives the stats of every generation
+    pub sink: Option<Box<dyn StatsSink>>,
+
+    /// What to do when the best score stops improving
+    pub stagnation: Option<Stagnation<B>>,
+
+    /// Random genomes still to be issued
+    immigrants: usize,
+
     /// Stats
     pub mean_score: f64,
     pub generations: i32,
     pub last_mean: f64,
     pub last_best: f64,
+    pub last_stats: Option<GenStats>,
     pub champion: Option<(f64, B::Genome)>,
     pub gens_without_improvement: i32,
 }
@@ -44,6 +81,29 @@ where
     }
 }
 
+impl<B> Evolver for Pool<B>
+where
+    B: Breeder,
+{
+    type Breeder = B;
+
+    fn breeder(&self) -> &B {
+        &self.breeder
+    }
+
+    fn next_genome(&mut self) -> (i32, B::Genome) {
+        self.next()
+    }
+
+    fn report_fitness(&mut self, id: i32, genome: B::Genome, fitness: f64) {
+        self.report(id, genome, fitness);
+    }
+
+    fn adopt(&mut self, parents: Vec<i32>, operator: Operator) -> i32 {
+        Pool::adopt(self, parents, operator)
+    }
+}
+
 /// Primary Pool implementation
 impl<B> Pool<B>
 where
@@ -64,39 +124,82 @@ where
                 cross: 0.45,
                 mutate: 0.45,
             },
+            adaptation: Adaptation::Fixed,
+            quality: Ratios {
+                top: 0.5,
+                random: 0.5,
+                cross: 0.5,
+                mutate: 0.5,
+            },
+            pending: HashMap::new(),
+            next_id: 1,
+            lineage: Lineage::new(),
+            prune_lineage: false,
+            gen_scores: vec![],
+            gen_operators: Ratios {
+                top: 0,
+                mutate: 0,
+                cross: 0,
+                random: 0,
+            },
+            improved: false,
+            sink: None,
+            stagnation: None,
+            immigrants: 0,
             mean_score: 0.0,
             generations: 0,
             reported: vec![],
             last_mean: -9999.0,
             last_best: -9999.0,
+            last_stats: None,
             champion: None,
             gens_without_improvement: 0,
         }
     }
 
+    /// Next genome with its id, the id is passed back to report
     fn next<F>(&mut self) -> (i32, F)
     where
         F: From<B::Genome>,
     {
+        let (op, parents, parent_score, next) = if self.immigrants > 0 {
+            self.immigrants -= 1;
+            (Operator::Random, vec![], None, self.breeder.random())
+        } else {
+            self.breed_next()
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.pending.insert(id, (op, parent_score, self.generations));
+        self.lineage.push(id, parents, op, self.generations);
+
+        (id, next.into())
+    }
+
+    /// Pick a method and create a genome, with its parents and the best parent's score
+    fn breed_next(&self) -> (Operator, Vec<i32>, Option<f64>, B::Genome) {
         if self.reported.len() <= 1 {
-            return (0, self.breeder.random().into());
+            return (Operator::Random, vec![], None, self.breeder.random());
         }
 
         // Let Pool Fill up
         let x = self.reported.len() as f64 / self.size as f64 * 10.0;
         if random() > x {
-            return (0, self.breeder.random().into());
+            return (Operator::Random, vec![], None, self.breeder.random());
         }
 
         let cum = self.ratios.cumulative();
-        let next = match random() {
+        match random() * cum.random {
             x if x < cum.top => {
                 // dbg!("Top");
-                self.reported.sample_weighted(3).1.clone()
+                let g = self.reported.sample_weighted(3);
+                (Operator::Top, vec![g.0], Some(g.2), g.1.clone())
             }
             x if x < cum.mutate => {
                 // dbg!("Mutate");
-                self.breeder.mutate(&self.reported.sample().1)
+                let g = self.reported.sample();
+                (Operator::Mutate, vec![g.0], Some(g.2), self.breeder.mutate(&g.1))
             }
             x if x < cum.cross => {
                 // dbg!("Cross");
@@ -104,31 +207,226 @@ where
                 let g1 = self.reported.sample();
                 let g2 = self.reported.sample();
 
-                if g1.2 > g2.2 {
-                    self.breeder.breed(&g1.1, &g2.1)
-                } else {
-                    self.breeder.breed(&g2.1, &g1.1)
-                }
+                let (g1, g2) = if g1.2 > g2.2 { (g1, g2) } else { (g2, g1) };
+                let next = self.breeder.breed(&g1.1, &g2.1);
+                (Operator::Cross, vec![g1.0, g2.0], Some(g1.2), next)
             }
             _ => {
                 // dbg!("Random");
-                self.breeder.random()
+                (Operator::Random, vec![], None, self.breeder.random())
             }
-        };
-
-        (0, next.into())
+        }
     }
 
     // fn report(&mut self, score: f64, gene: B::Genome) {
-    pub fn report<F>(&mut self, species_id: i32, genome: F, score: f64) -> bool
+    pub fn report<F>(&mut self, id: i32, genome: F, score: f64) -> bool
     where
         F: Into<B::Genome>,
     {
-        self.reported.push((0, genome.into(), score));
+        if let Some((op, parent_score, _)) = self.pending.remove(&id) {
+            // Random genomes compete with the pool average
+            let baseline = parent_score.unwrap_or_else(|| {
+                self.reported.iter().map(|r| r.2).sum::<f64>() / self.reported.len().max(1) as f64
+            });
+            self.adapt(op, if score > baseline { 1.0 } else { 0.0 });
+            *self.gen_operators.get_mut(op) += 1;
+        }
+
+        self.lineage.score(id, score);
+
+        let genome = genome.into();
+        match &self.champion {
+            Some((s, _)) if *s >= score => (),
+            _ => {
+                self.champion = Some((score, genome.clone()));
+                self.improved = true;
+            }
+        }
+
+        self.reported.push((id, genome, score));
         self.cull_weak();
+
+        // A generation passes every size reports
+        self.gen_scores.push(score);
+        if self.gen_scores.len() >= self.size {
+            self.end_generation();
+        }
         true
     }
 
+    /// Breeder of the pool
+    pub fn breeder(&self) -> &B {
+        &self.breeder
+    }
+
+    /// Id for a genome bred outside the pool, recorded in the lineage
+    pub fn adopt(&mut self, parents: Vec<i32>, operator: Operator) -> i32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.lineage.push(id, parents, operator, self.generations);
+        id
+    }
+
+    /// Best genomes in the pool with their scores
+    pub fn best(&self, n: usize) -> Vec<(B::Genome, f64)> {
+        let mut best: Vec<_> = self.reported.iter().map(|r| (r.1.clone(), r.2)).collect();
+        best.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        best.truncate(n);
+        best
+    }
+
+    /// Add a genome scored elsewhere, its lineage in this pool starts as a random genome
+    pub fn immigrate(&mut self, genome: B::Genome, score: f64) -> i32 {
+        let id = self.adopt(vec![], Operator::Random);
+        self.lineage.score(id, score);
+        self.reported.push((id, genome, score));
+        self.cull_weak();
+        id
+    }
+
+    /// Summarize the generation, update the stats and pass them to the sink
+    fn end_generation(&mut self) {
+        let mut stats = GenStats::from_scores(self.generations, &self.gen_scores);
+        stats.species = self.count_species();
+        stats.genome_size = self
+            .reported
+            .iter()
+            .map(|r| self.breeder.size(&r.1) as f64)
+            .sum::<f64>()
+            / self.reported.len().max(1) as f64;
+        stats.operators = self.gen_operators.clone();
+        stats.ratios = self.ratios.clone();
+
+        self.mean_score =
+            self.reported.iter().map(|r| r.2).sum::<f64>() / self.reported.len().max(1) as f64;
+        self.last_mean = stats.mean;
+        self.last_best = stats.best;
+        if self.improved {
+            self.gens_without_improvement = 0;
+        } else {
+            self.gens_without_improvement += 1;
+        }
+
+        if let Some(sink) = &mut self.sink {
+            if let Err(e) = sink.record(&stats) {
+                eprintln!("Failed to record stats: {}", e);
+            }
+        }
+
+        let previous = self.generations - 1;
+        self.pending.retain(|_, p| p.2 >= previous);
+        if self.prune_lineage {
+            let living = self.reported.iter().map(|r| r.0).chain(self.pending.keys().copied());
+            self.lineage.prune(living.collect::<Vec<_>>());
+        }
+
+        self.generations += 1;
+        self.gen_scores.clear();
+        self.gen_operators = Ratios {
+            top: 0,
+            mutate: 0,
+            cross: 0,
+            random: 0,
+        };
+        self.improved = false;
+        self.last_stats = Some(stats);
+
+        let stagnated = matches!(
+            &self.stagnation,
+            Some(s) if self.gens_without_improvement >= s.generations
+        );
+        if stagnated {
+            self.restart();
+        }
+    }
+
+    /// Apply the stagnation restart strategy
+    fn restart(&mut self) {
+        let mut stagnation = match self.stagnation.take() {
+            Some(s) => s,
+            None => return,
+        };
+
+        match &mut stagnation.restart {
+            Restart::Immigrants(n) => self.immigrants = *n,
+            Restart::RaiseMutation(factor) => {
+                self.ratios.mutate *= *factor;
+                self.ratios.random *= *factor;
+                let total = self.ratios.total();
+                for op in Operator::ALL.iter() {
+                    *self.ratios.get_mut(*op) /= total;
+                }
+            }
+            Restart::KeepElite(n) => {
+                self.reported
+                    .sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+                self.reported.truncate(*n);
+            }
+            Restart::Callback(f) => f(self),
+        }
+
+        // Genomes issued before the restart no longer credit their operators
+        self.pending.clear();
+        self.gens_without_improvement = 0;
+        self.stagnation = Some(stagnation);
+    }
+
+    /// Number of species in the pool, each genome joins the first species it matches
+    fn count_species(&self) -> usize {
+        let mut representatives: Vec<&B::Genome> = vec![];
+        for r in self.reported.iter() {
+            if !representatives.iter().any(|g| self.breeder.is_same(g, &r.1)) {
+                representatives.push(&r.1);
+            }
+        }
+        representatives.len()
+    }
+
+    /// Credit a method with a reward and update the ratios
+    fn adapt(&mut self, op: Operator, reward: f64) {
+        let (alpha, p_min) = match self.adaptation {
+            Adaptation::Fixed => return,
+            Adaptation::ProbabilityMatching { alpha, p_min } => (alpha, p_min),
+            Adaptation::AdaptivePursuit { alpha, p_min, .. } => (alpha, p_min),
+        };
+
+        let q = self.quality.get_mut(op);
+        *q += alpha * (reward - *q);
+
+        let p_max = 1.0 - (Operator::ALL.len() - 1) as f64 * p_min;
+        match self.adaptation {
+            Adaptation::ProbabilityMatching { .. } => {
+                let total = self.quality.total();
+                for op in Operator::ALL.iter() {
+                    let q = if total > 0.0 {
+                        self.quality.get(*op) / total
+                    } else {
+                        1.0 / Operator::ALL.len() as f64
+                    };
+                    *self.ratios.get_mut(*op) = p_min + (1.0 - Operator::ALL.len() as f64 * p_min) * q;
+                }
+            }
+            Adaptation::AdaptivePursuit { beta, .. } => {
+                let best = Operator::ALL
+                    .iter()
+                    .cloned()
+                    .max_by(|a, b| {
+                        self.quality
+                            .get(*a)
+                            .partial_cmp(&self.quality.get(*b))
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    })
+                    .unwrap();
+                for op in Operator::ALL.iter() {
+                    let target = if *op == best { p_max } else { p_min };
+                    let p = self.ratios.get_mut(*op);
+                    *p += beta * (target - *p);
+                }
+            }
+            Adaptation::Fixed => (),
+        }
+    }
+
     fn cull_weak(&mut self) {
         if self.reported.len() <= 10 {
             return;
@@ -138,8 +436,8 @@ where
 
         // Create normal distribution
         let (mean, std) = scores.std();
-        let stats = Normal::new(mean, std).unwrap();
-        eprintln!("{}, {}", mean, std);
+        // Equal scores have no spread
+        let stats = Normal::new(mean, std.max(1e-9)).unwrap();
 
         // Sort scores
         self.reported.sort_by(|a, b| {
@@ -154,7 +452,6 @@ where
         let max_size = self.size as f64;
 
         // Probabilities
-        let len = self.reported.len();
         self.reported.retain(|r|{
             // Get the Cumulated Distribution probability
             let x = stats.cdf(r.2);
@@ -167,8 +464,6 @@ where
             }
             true
         });
-
-        eprintln!("{} => {}", len, self.reported.len())
     }
 }
 
@@ -182,9 +477,70 @@ fn transform_prob(x: f64, y: f64) -> f64{
     a * x / (a*x + (1.0-x) / a)
 }
 
+/// Breed strategy that produced a genome
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
+    Top,
+    Mutate,
+    Cross,
+    Random,
+}
+
+impl Operator {
+    pub const ALL: [Operator; 4] = [
+        Operator::Top,
+        Operator::Mutate,
+        Operator::Cross,
+        Operator::Random,
+    ];
+}
+
+/// How the pool adapts its ratios to the success of each strategy.
+/// A strategy succeeds when its genome beats its best parent,
+/// or the pool average for random genomes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Adaptation {
+    /// Keep the ratios as set
+    Fixed,
+    /// Ratios proportional to success, at least p_min each
+    ProbabilityMatching { alpha: f64, p_min: f64 },
+    /// Ratios pursue the most successful strategy at rate beta
+    AdaptivePursuit { alpha: f64, beta: f64, p_min: f64 },
+}
+
+/// Hook run on a pool, e.g. to restart it by hand
+pub type GenerationHook<B> = Box<dyn FnMut(&mut Pool<B>) + Send + Sync>;
+
+/// Restart strategy of a stagnated pool
+pub enum Restart<B: Breeder> {
+    /// Issue this many random genomes next
+    Immigrants(usize),
+    /// Scale the mutate and random ratios
+    RaiseMutation(f64),
+    /// Drop all but the best genomes, the pool refills with random ones
+    KeepElite(usize),
+    /// Run a custom hook on the pool
+    Callback(GenerationHook<B>),
+}
+
+/// Restart a pool when its best score has not improved for a number of generations
+pub struct Stagnation<B: Breeder> {
+    pub generations: i32,
+    pub restart: Restart<B>,
+}
+
+impl<B: Breeder> Stagnation<B> {
+    pub fn new(generations: i32, restart: Restart<B>) -> Self {
+        Self {
+            generations,
+            restart,
+        }
+    }
+}
+
 /// Define ratio of different breed strategies
 /// Templated for easy convertion
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Ratios<T>
 where
     T: Clone + Copy,
@@ -195,24 +551,35 @@ where
     pub random: T,
 }
 
-impl Ratios<f32> {
-    /// Convert into integer ratios
-    fn to_int(&self, max: usize) -> Ratios<usize> {
-        let top = (max as f32 * self.top).floor() as usize;
-        let mutate = (max as f32 * self.mutate).floor() as usize;
-        let cross = (max as f32 * self.cross).floor() as usize;
-        Ratios {
-            top,
-            mutate,
-            cross,
-            random: max - top - mutate - cross,
+impl<T> Ratios<T>
+where
+    T: Clone + Copy,
+{
+    pub fn get(&self, op: Operator) -> T {
+        match op {
+            Operator::Top => self.top,
+            Operator::Mutate => self.mutate,
+            Operator::Cross => self.cross,
+            Operator::Random => self.random,
         }
     }
 
+    pub fn get_mut(&mut self, op: Operator) -> &mut T {
+        match op {
+            Operator::Top => &mut self.top,
+            Operator::Mutate => &mut self.mutate,
+            Operator::Cross => &mut self.cross,
+            Operator::Random => &mut self.random,
+        }
+    }
 }
 
 impl<T> Ratios<T> where T: std::ops::Add<T, Output=T> + Copy + Clone {
-    fn cumulative(&self) -> Self {
+    pub fn total(&self) -> T {
+        self.top + self.mutate + self.cross + self.random
+    }
+
+    pub(crate) fn cumulative(&self) -> Self {
         Self {
             top: self.top,
             mutate: self.top + self.mutate,
@@ -239,6 +606,167 @@ mod test {
         // pool.cull_weak();
     }
 
+    #[test]
+    fn test_adaptive_pursuit() {
+        let mut pool = Pool::new(100, FloatBreeder::default());
+        pool.adaptation = Adaptation::AdaptivePursuit {
+            alpha: 0.1,
+            beta: 0.1,
+            p_min: 0.05,
+        };
+
+        // Only mutation ever improves
+        for _ in 0..100 {
+            pool.adapt(Operator::Mutate, 1.0);
+            pool.adapt(Operator::Cross, 0.0);
+        }
+        assert!(pool.ratios.mutate > 0.8);
+        assert!((pool.ratios.total() - 1.0).abs() < 1e-9);
+        assert!(pool.ratios.cross < 0.1);
+    }
+
+    #[test]
+    fn test_probability_matching() {
+        let mut pool = Pool::new(100, FloatBreeder::default());
+        pool.adaptation = Adaptation::ProbabilityMatching {
+            alpha: 0.1,
+            p_min: 0.05,
+        };
+        for i in 0..500 {
+            let (id, f): (_, f64) = pool.next();
+            pool.report(id, f, i as f64);
+        }
+        assert!((pool.ratios.total() - 1.0).abs() < 1e-9);
+        assert!(Operator::ALL.iter().all(|op| pool.ratios.get(*op) >= 0.05 - 1e-9));
+    }
+
+    #[test]
+    fn test_lineage() {
+        let mut pool = Pool::new(20, FloatBreeder::default());
+        for _ in 0..200 {
+            let (id, f): (_, f64) = pool.next();
+            pool.report(id, f, f);
+        }
+        assert_eq!(pool.lineage.len(), 200);
+        assert_eq!(pool.generations, 10);
+
+        // Every parent was issued before its child
+        for r in pool.lineage.records() {
+            assert!(r.parents.iter().all(|p| *p < r.id && pool.lineage.get(*p).is_some()));
+            assert_eq!(r.parents.is_empty(), r.operator == Operator::Random);
+        }
+    }
+
+    #[test]
+    fn test_prune_lineage() {
+        let mut pool = Pool::new(20, FloatBreeder::default());
+        pool.prune_lineage = true;
+        for _ in 0..2000 {
+            let (id, f): (_, f64) = pool.next();
+            pool.report(id, f, f);
+        }
+        assert!(pool.lineage.len() < 2000);
+        for r in pool.reported.iter() {
+            assert!(pool.lineage.get(r.0).is_some());
+        }
+        for r in pool.lineage.records() {
+            assert!(r.parents.iter().all(|p| pool.lineage.get(*p).is_some()));
+        }
+    }
+
+    #[test]
+    fn test_generation_stats() {
+        let mut pool = Pool::new(20, FloatBreeder::default());
+        pool.sink = Some(Box::new(Vec::<GenStats>::new()));
+        for i in 0..100 {
+            let (id, f): (_, f64) = pool.next();
+            pool.report(id, f, i as f64);
+        }
+
+        let stats = pool.last_stats.as_ref().unwrap();
+        assert_eq!(stats.generation, 4);
+        assert_eq!(stats.best, 99.0);
+        assert_eq!(stats.median, 89.5);
+        assert_eq!(stats.operators.total(), 20);
+        assert_eq!(stats.genome_size, 1.0);
+        assert!(stats.species >= 1);
+        assert_eq!(pool.last_best, 99.0);
+        assert_eq!(pool.gens_without_improvement, 0);
+    }
+
+    #[test]
+    fn test_immigrate() {
+        let mut pool = Pool::new(10, FloatBreeder::default());
+        let (first, _): (_, f64) = pool.next();
+        let id = pool.immigrate(0.5, 2.0);
+        assert_ne!(id, first);
+        assert_eq!(pool.lineage.get(id).unwrap().score, Some(2.0));
+        assert_eq!(pool.reported.last().unwrap().0, id);
+    }
+
+    #[test]
+    fn test_pending() {
+        let mut pool = Pool::new(10, FloatBreeder::default());
+
+        // Genomes that are never reported are dropped after the next generation
+        let dropped: Vec<(i32, f64)> = (&mut pool).take(5).collect();
+        stagnate(&mut pool, 3);
+        assert!(pool.pending.len() <= 10);
+        assert!(dropped.iter().all(|(id, _)| !pool.pending.contains_key(id)));
+    }
+
+    /// Pool whose scores never improve after the first generation
+    fn stagnate(pool: &mut Pool<FloatBreeder>, generations: usize) {
+        for _ in 0..generations * 10 {
+            let (id, f): (_, f64) = pool.next();
+            pool.report(id, f, 0.0);
+        }
+    }
+
+    #[test]
+    fn test_stagnation() {
+        let mut pool = Pool::new(10, FloatBreeder::default());
+        pool.stagnation = Some(Stagnation::new(3, Restart::Immigrants(5)));
+        stagnate(&mut pool, 3);
+        assert_eq!(pool.gens_without_improvement, 2);
+        stagnate(&mut pool, 1);
+        assert_eq!(pool.gens_without_improvement, 0);
+        assert_eq!(pool.immigrants, 5);
+        assert!(pool.pending.is_empty());
+
+        let mut pool = Pool::new(10, FloatBreeder::default());
+        pool.stagnation = Some(Stagnation::new(2, Restart::RaiseMutation(2.0)));
+        stagnate(&mut pool, 3);
+        assert!(pool.ratios.mutate > 0.45);
+        assert!((pool.ratios.total() - 1.0).abs() < 1e-9);
+
+        let mut pool = Pool::new(10, FloatBreeder::default());
+        pool.stagnation = Some(Stagnation::new(2, Restart::KeepElite(1)));
+        stagnate(&mut pool, 3);
+        assert_eq!(pool.reported.len(), 1);
+
+        let mut pool = Pool::new(10, FloatBreeder::default());
+        pool.stagnation = Some(Stagnation::new(
+            2,
+            Restart::Callback(Box::new(|p: &mut Pool<FloatBreeder>| p.last_best = 42.0)),
+        ));
+        stagnate(&mut pool, 3);
+        assert_eq!(pool.last_best, 42.0);
+    }
+
+    #[test]
+    fn test_adopt() {
+        let mut pool = Pool::new(10, FloatBreeder::default());
+        let (id, f): (_, f64) = pool.next();
+        let child = pool.adopt(vec![id], Operator::Mutate);
+        pool.report(child, pool.breeder().mutate(&f), 1.0);
+
+        let r = pool.lineage.get(child).unwrap();
+        assert_eq!(r.parents, vec![id]);
+        assert_eq!(r.score, Some(1.0));
+        assert_eq!(pool.best(1)[0].1, 1.0);
+    }
+
     #[test]
     fn print_prob() {
         let mut vec: Vec<_> = vec![];
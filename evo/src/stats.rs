@@ -0,0 +1,232 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::pool::Ratios;
+
+//////////////////////////////////
+/// Generation Stats
+/// Summary of one generation of a pool
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenStats {
+    pub generation: i32,
+    /// Fitness of the genomes reported this generation
+    pub best: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub std: f64,
+    /// Species and mean genome size of the pool
+    pub species: usize,
+    pub genome_size: f64,
+    /// Genomes reported this generation by the operator that made them
+    pub operators: Ratios<usize>,
+    /// Operator ratios at the end of the generation
+    pub ratios: Ratios<f64>,
+}
+
+impl GenStats {
+    /// Fitness summary of a generation's scores
+    pub fn from_scores(generation: i32, scores: &[f64]) -> Self {
+        let mut sorted = scores.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let n = sorted.len().max(1) as f64;
+        let mean = sorted.iter().sum::<f64>() / n;
+        let std = (sorted.iter().map(|s| (s - mean) * (s - mean)).sum::<f64>() / n).sqrt();
+        let median = match sorted.len() {
+            0 => 0.0,
+            l if l % 2 == 0 => (sorted[l / 2 - 1] + sorted[l / 2]) / 2.0,
+            l => sorted[l / 2],
+        };
+
+        Self {
+            generation,
+            best: sorted.last().cloned().unwrap_or(0.0),
+            mean,
+            median,
+            std,
+            species: 0,
+            genome_size: 0.0,
+            operators: Ratios {
+                top: 0,
+                mutate: 0,
+                cross: 0,
+                random: 0,
+            },
+            ratios: Ratios {
+                top: 0.0,
+                mutate: 0.0,
+                cross: 0.0,
+                random: 0.0,
+            },
+        }
+    }
+
+    pub const CSV_HEADER: &'static str = "generation,best,mean,median,std,species,genome_size,\
+        op_top,op_mutate,op_cross,op_random,ratio_top,ratio_mutate,ratio_cross,ratio_random";
+
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.generation,
+            self.best,
+            self.mean,
+            self.median,
+            self.std,
+            self.species,
+            self.genome_size,
+            self.operators.top,
+            self.operators.mutate,
+            self.operators.cross,
+            self.operators.random,
+            self.ratios.top,
+            self.ratios.mutate,
+            self.ratios.cross,
+            self.ratios.random,
+        )
+    }
+
+    /// Single line JSON object
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"generation\":{},\"best\":{},\"mean\":{},\"median\":{},\"std\":{},\
+             \"species\":{},\"genome_size\":{},\
+             \"operators\":{{\"top\":{},\"mutate\":{},\"cross\":{},\"random\":{}}},\
+             \"ratios\":{{\"top\":{},\"mutate\":{},\"cross\":{},\"random\":{}}}}}",
+            self.generation,
+            json_num(self.best),
+            json_num(self.mean),
+            json_num(self.median),
+            json_num(self.std),
+            self.species,
+            json_num(self.genome_size),
+            self.operators.top,
+            self.operators.mutate,
+            self.operators.cross,
+            self.operators.random,
+            json_num(self.ratios.top),
+            json_num(self.ratios.mutate),
+            json_num(self.ratios.cross),
+            json_num(self.ratios.random),
+        )
+    }
+}
+
+/// JSON has no infinities or NaN
+fn json_num(x: f64) -> String {
+    if x.is_finite() {
+        x.to_string()
+    } else {
+        "null".into()
+    }
+}
+
+//////////////////////////////////
+/// Stats Sink
+/// Receives the stats of every generation
+pub trait StatsSink: Send + Sync {
+    fn record(&mut self, stats: &GenStats) -> io::Result<()>;
+}
+
+/// Keep stats in memory
+impl StatsSink for Vec<GenStats> {
+    fn record(&mut self, stats: &GenStats) -> io::Result<()> {
+        self.push(stats.clone());
+        Ok(())
+    }
+}
+
+//////////////////////////////////
+/// CSV Sink
+/// One row per generation after a header
+pub struct CsvSink<W: Write> {
+    out: W,
+    header: bool,
+}
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(out: W) -> Self {
+        Self { out, header: false }
+    }
+}
+
+impl CsvSink<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Send + Sync> StatsSink for CsvSink<W> {
+    fn record(&mut self, stats: &GenStats) -> io::Result<()> {
+        if !self.header {
+            self.header = true;
+            writeln!(self.out, "{}", GenStats::CSV_HEADER)?;
+        }
+        writeln!(self.out, "{}", stats.to_csv())?;
+        self.out.flush()
+    }
+}
+
+//////////////////////////////////
+/// JSON Lines Sink
+/// One JSON object per generation and line
+pub struct JsonSink<W: Write> {
+    out: W,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+}
+
+impl JsonSink<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Send + Sync> StatsSink for JsonSink<W> {
+    fn record(&mut self, stats: &GenStats) -> io::Result<()> {
+        writeln!(self.out, "{}", stats.to_json())?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assert_delta;
+
+    #[test]
+    fn test_from_scores() {
+        let stats = GenStats::from_scores(3, &[4.0, 1.0, 3.0, 2.0]);
+        assert_delta!(stats.best, 4.0, 1e-9);
+        assert_delta!(stats.mean, 2.5, 1e-9);
+        assert_delta!(stats.median, 2.5, 1e-9);
+        assert_delta!(stats.std, 1.25f64.sqrt(), 1e-9);
+    }
+
+    #[test]
+    fn test_sinks() {
+        let stats = GenStats::from_scores(0, &[1.0]);
+
+        let mut csv = CsvSink::new(vec![]);
+        csv.record(&stats).unwrap();
+        csv.record(&stats).unwrap();
+        let text = String::from_utf8(csv.out).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], GenStats::CSV_HEADER);
+        assert_eq!(
+            lines[1].split(',').count(),
+            GenStats::CSV_HEADER.split(',').count()
+        );
+
+        let mut json = JsonSink::new(vec![]);
+        json.record(&stats).unwrap();
+        let text = String::from_utf8(json.out).unwrap();
+        assert!(text.starts_with("{\"generation\":0,\"best\":1,"));
+        assert!(text.ends_with("}}\n"));
+    }
+}
@@ -14,6 +14,12 @@ pub fn random_clamp(min: f64, max: f64) -> f64 {
     rand::random::<f64>() * (max - min) + min
 }
 
+/// Standard normal sample (Box-Muller)
+pub fn random_normal() -> f64 {
+    let u = 1.0 - random();
+    (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * random()).cos()
+}
+
 pub fn random_i(max: usize) -> usize {
     if max == 0 {
         return 0;
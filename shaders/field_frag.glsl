@@ -7,25 +7,43 @@ out vec4 f_color;
 uniform int PASS_NUM;
 
 uniform sampler2D TEX;
+uniform sampler2D WALLS;
 
 vec2 uv_norm = (uv + 1.0) / 2.0;
 
+bool wall(vec2 p) {
+    return texture(WALLS, p).r > 0.5;
+}
+
+// Neighbour value, walls reflect the current one so nothing flows into them
+vec4 neighbour(vec2 offset, vec4 cur) {
+    vec2 p = uv_norm + offset;
+    return wall(p) ? cur : texture2D(TEX, p);
+}
+
 vec3 render() {
     if (PASS_NUM == 0) {
+        if (wall(uv_norm)) {
+            return vec3(0.0);
+        }
+
         vec2 textureSize2d = textureSize(TEX,0);
         vec2 texelSize = vec2(1.0) / textureSize2d;
+        vec4 cur = texture2D(TEX, uv_norm);
         vec4 value =
-            texture2D(TEX, uv_norm + vec2(-1.0, 0.0) * texelSize) +
-            texture2D(TEX, uv_norm + vec2(0.0, 1.0) * texelSize) +
-            texture2D(TEX, uv_norm + vec2(0.0, -1.0) * texelSize) +
-            texture2D(TEX, uv_norm + vec2(1.0, 0.0) * texelSize);
+            neighbour(vec2(-1.0, 0.0) * texelSize, cur) +
+            neighbour(vec2(0.0, 1.0) * texelSize, cur) +
+            neighbour(vec2(0.0, -1.0) * texelSize, cur) +
+            neighbour(vec2(1.0, 0.0) * texelSize, cur);
 
-        vec4 cur = texture2D(TEX, uv_norm);
         float dt = 0.80;
         vec3 output = ((1 - dt) * cur + dt * value / 4.0).xyz;
 
         return output * 0.994;
     } else {
+        if (wall(uv_norm)) {
+            return vec3(0.4);
+        }
         return texture(TEX, (uv + 1.0) / 2.0).xyz * 10;
         /* return vec3(0.0, 1.0, 0.0); */
     }
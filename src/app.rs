@@ -1,8 +1,14 @@
 use crate::breeder::*;
 use crate::creature::*;
+use crate::map::{Map, Tile};
 use crate::prelude::*;
+use crate::senses::{Actuator, Sensor};
+use crate::spatial::SpatialIndex;
 use crate::systems::*;
 use evo::pool::Ratios;
+use evo::stats::CsvSink;
+use legion::systems::Builder;
+use std::path::PathBuf;
 
 pub struct App {
     pub world: World,
@@ -11,31 +17,63 @@ pub struct App {
 }
 
 impl App {
-    pub fn new() -> Self {
+    /// New world, laid out by a map when given,
+    /// the stats of the first colony are written to a CSV file when given
+    pub fn new(map: Option<Map>, stats: Option<PathBuf>) -> Self {
         // Create World
         let mut world = World::default();
 
-        // Instantiate resources
-        // Pool
-        let mut pool = Pool::new(200, AntBreeder::default());
-        pool.ratios = Ratios::<f64> {
-            top: 0.1,
-            mutate: 0.45,
-            cross: 0.43,
-            random: 0.02,
-        };
-
         // Config
         let bounds = Rect::new(0, 0, 100, 100);
-        let config = Config { bounds };
+        let config = Config {
+            bounds,
+            map,
+            food_energy: 3.0,
+            food_regrow: 0.05,
+            bite: 2.0,
+            carry: 1.0,
+            emit_strength: 10.0,
+            emit_cost: 0.05,
+            vision: Vision::default(),
+            sensors: vec![
+                Sensor::Gradient(FOOD_SCENT),
+                Sensor::Constant(0.0),
+                Sensor::Vision,
+                Sensor::Gradient(1),
+                Sensor::Gradient(2),
+                Sensor::Nest,
+                Sensor::Carrying,
+            ],
+            locomotion: Locomotion::default(),
+            physics: Physics::default(),
+            reproduction: None,
+            actuators: vec![Actuator::Drive, Actuator::Emit(1), Actuator::Emit(2)],
+        };
 
-        // Add Creatures' components
-        for (id, g) in (&mut pool).take(30) {
-            world.push(Creature::new(id, g, &config));
+        // Nests and food sources of the map, or a nest in the middle and scattered food
+        let centers = |tile| match &config.map {
+            Some(map) => map
+                .find(tile)
+                .into_iter()
+                .map(|(col, row)| map.center(col, row, &config.bounds))
+                .collect(),
+            None => vec![],
+        };
+        let (mut nests, sources): (Vec<Position>, Vec<Position>) =
+            (centers(Tile::Nest), centers(Tile::Food));
+        if nests.is_empty() {
+            nests.push(Position { x: 50.0, y: 50.0 });
         }
 
-        for _ in 0..40 {
-            world.push(Food::new(vec![30.0, 0.0, 0.0], &config));
+        if sources.is_empty() {
+            for _ in 0..40 {
+                world.push(Food::with_scent(30.0, &config));
+            }
+        }
+        for position in sources {
+            let mut food = Food::with_scent(30.0, &config);
+            food.0.position = position;
+            world.push(food);
         }
 
         // Setup event handlers
@@ -46,18 +84,47 @@ impl App {
 
         // Create resources
         let mut resources = Resources::default();
+        resources.insert(SpatialIndex::new(&config.bounds, 5.0));
         resources.insert(config);
-        resources.insert(pool);
         resources.insert(time);
 
         // Set up Update Schedule
-        let mut schedule = Schedule::builder()
+        let mut builder = Schedule::builder();
+        builder
+            .add_system(update_index_system())
+            .add_system(update_vision_system())
             .add_system(update_emitters_system())
-            .add_system(detect_system())
-            .add_system(update_networks_system())
-            .add_system(update_energy_system())
-            .add_system(remove_dead_system())
-            .build();
+            .add_system(regrow_food_system())
+            .add_system(update_motion_system())
+            .add_system(resolve_collisions_system());
+
+        // Add a competing ant colony for every nest
+        for (i, nest) in nests.into_iter().enumerate() {
+            let mut pool = Pool::new(200, AntBreeder::new(&resources.get::<Config>().unwrap()));
+            pool.ratios = Ratios::<f64> {
+                top: 0.1,
+                mutate: 0.45,
+                cross: 0.43,
+                random: 0.02,
+            };
+            pool.prune_lineage = true;
+
+            // Stats of the first colony
+            if let Some(path) = stats.as_ref().filter(|_| i == 0) {
+                pool.sink = match CsvSink::create(path) {
+                    Ok(sink) => Some(Box::new(sink)),
+                    Err(e) => {
+                        eprintln!("Not logging stats to {}: {}", path.display(), e);
+                        None
+                    }
+                };
+            }
+
+            let colony = Colony(i);
+            Self::add_colony(&mut world, &mut resources, &mut builder, colony, nest, pool, 30);
+        }
+
+        let schedule = builder.build();
 
         Self {
             world,
@@ -66,6 +133,68 @@ impl App {
         }
     }
 
+    /// Add an evolving colony with its nest and creatures,
+    /// the systems of a population are added with its first colony
+    pub fn add_colony<E: ColonyEvolver>(
+        world: &mut World,
+        resources: &mut Resources,
+        builder: &mut Builder,
+        colony: Colony,
+        nest: Position,
+        mut evolver: E,
+        count: usize,
+    ) {
+        world.push(Nest::new(nest, colony));
+
+        // Add Creatures' components
+        if let Some(config) = resources.get::<Config>() {
+            for _ in 0..count {
+                let (id, g) = evolver.next_genome();
+                world.push(E::Breeder::spawn(colony, id, g, &config));
+            }
+        }
+
+        if !resources.contains::<Colonies<E>>() {
+            resources.insert(Colonies::<E>::default());
+            builder
+                .add_system(detect_system::<E>())
+                .add_system(update_networks_system::<E>())
+                .add_system(update_energy_system::<E>())
+                .add_system(reproduce_system::<E>())
+                .add_system(remove_dead_system::<E>());
+        }
+        resources.get_mut::<Colonies<E>>().unwrap().insert(colony, evolver);
+    }
+
+    /// Write the ancestry of the best ant of all colonies next to the stats
+    pub fn export_lineage(&self) {
+        let colonies = match self.resources.get::<Colonies<AntPool>>() {
+            Some(colonies) => colonies,
+            None => return,
+        };
+        let (pool, best) = match colonies
+            .iter()
+            .filter_map(|(_, pool)| pool.lineage.best().map(|r| (pool, r)))
+            .max_by(|a, b| {
+                a.1.score
+                    .partial_cmp(&b.1.score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            }) {
+            Some((pool, r)) => (pool, r.id),
+            None => return,
+        };
+
+        let files = [
+            ("lineage.dot", pool.lineage.to_dot(best)),
+            ("lineage.json", pool.lineage.to_json(best)),
+        ];
+        for (path, text) in files.iter() {
+            if let Err(e) = std::fs::write(path, text) {
+                eprintln!("Could not write {}: {}", path, e);
+            }
+        }
+    }
+
     pub fn update(&mut self, dt: f64) {
         // update time
         if let Some(mut time) = self.resources.get_mut::<Time>() {
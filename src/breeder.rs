@@ -1,11 +1,13 @@
-use evo::{NeatBreeder, Pool, VecBreeder, derive_breeder, Breeder};
+use crate::resources::Config;
+use evo::{NeatBreeder, Pool, VecBreeder, Breeder};
 
 //////////////////////////////////
 /// Primary Creature Breeder Struct
 ///
-#[derive_breeder]
+#[derive(Breeder)]
+#[breeder(genome = "AntGenome")]
 pub struct AntBreeder {
-    #[breeder(0.1)]
+    #[breeder(0.1, distance = 0.1)]
     pub color: VecBreeder,
 
     #[breeder(0.9)]
@@ -13,11 +15,11 @@ pub struct AntBreeder {
 }
 
 /// type Aliases
-pub type AntGenome = <AntBreeder as Breeder>::Genome;
 pub type AntPool = Pool<AntBreeder>;
 
-impl Default for AntBreeder {
-    fn default() -> Self {
+impl AntBreeder {
+    /// Breeder for the senses in the config
+    pub fn new(c: &Config) -> Self {
         let mut breeder = VecBreeder::default();
         breeder.size = 4;
         breeder.min = 0.0;
@@ -26,8 +28,8 @@ impl Default for AntBreeder {
         Self {
             color: breeder,
             network: NeatBreeder {
-                inputs: 3,
-                outputs: 2,
+                inputs: c.inputs(),
+                outputs: c.outputs(),
                 ..NeatBreeder::default()
             }
         }
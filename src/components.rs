@@ -1,4 +1,8 @@
+use crate::map::Map;
+use crate::resources::Walls;
+use crate::spatial::{angle_between, SpatialIndex};
 use crate::utils::{Color, Position, Rect};
+use legion::Entity;
 use evo::utils::random;
 use evo::NeatGenome;
 use evo::NeatNetwork;
@@ -6,7 +10,10 @@ use evo::NeatNetwork;
 #[derive(Clone, Debug, Copy, PartialEq)]
 pub enum BodyType {
     Food,
-    Creature
+    Creature,
+    Nest,
+    /// Static and solid
+    Obstacle,
 }
 
 ///////////////////////////////
@@ -19,6 +26,9 @@ pub struct Body {
     pub color: Color,
     pub position: Position,
     pub theta: f64,
+    pub velocity: (f64, f64),
+    /// Food carried back to the nest
+    pub carrying: f64,
     pub emits: Vec<f64>,
     pub radius: f64,
     pub history: std::collections::VecDeque<(f64, f64)>,
@@ -59,6 +69,11 @@ impl Body {
         }
     }
 
+    /// Speed along any direction
+    pub fn speed(&self) -> f64 {
+        (self.velocity.0 * self.velocity.0 + self.velocity.1 * self.velocity.1).sqrt()
+    }
+
     // Distance to another body
     pub fn dist_sq(&self, b: &Body) -> f64 {
         self.position.dist_sq(&b.position)
@@ -70,6 +85,60 @@ impl Body {
         self.position.dist_sq(&b.position) < r * r
     }
 
+    /// Direction away from a circle and depth if the body overlaps it
+    pub fn overlap(&self, position: &Position, radius: f64) -> Option<((f64, f64), f64)> {
+        let (dx, dy) = (self.position.x - position.x, self.position.y - position.y);
+        let r = self.radius + radius;
+        let d2 = dx * dx + dy * dy;
+        if d2 >= r * r {
+            return None;
+        }
+
+        let d = d2.sqrt();
+        if d < 1e-9 {
+            // Same centre, pick any direction
+            let theta = random() * std::f64::consts::TAU;
+            return Some(((theta.sin(), theta.cos()), r));
+        }
+        Some(((dx / d, dy / d), r - d))
+    }
+
+    /// Stop or reflect the velocity going against a surface normal
+    pub fn hit(&mut self, normal: (f64, f64), walls: Walls) {
+        let vn = self.velocity.0 * normal.0 + self.velocity.1 * normal.1;
+        if vn >= 0.0 {
+            return;
+        }
+
+        let k = match walls {
+            Walls::Slide => 1.0,
+            Walls::Bounce(restitution) => 1.0 + restitution,
+        };
+        self.velocity.0 -= k * vn * normal.0;
+        self.velocity.1 -= k * vn * normal.1;
+    }
+
+    /// Keep the body inside the bounds, hitting the walls it crosses
+    pub fn collide_bounds(&mut self, bounds: &Rect, walls: Walls) {
+        let r = self.radius;
+        let (left, right) = (bounds.left() as f64 + r, bounds.right() as f64 - r);
+        let (top, bottom) = (bounds.top() as f64 + r, bounds.bottom() as f64 - r);
+        if self.position.x < left {
+            self.position.x = left;
+            self.hit((1.0, 0.0), walls);
+        } else if self.position.x > right {
+            self.position.x = right;
+            self.hit((-1.0, 0.0), walls);
+        }
+        if self.position.y < top {
+            self.position.y = top;
+            self.hit((0.0, 1.0), walls);
+        } else if self.position.y > bottom {
+            self.position.y = bottom;
+            self.hit((0.0, -1.0), walls);
+        }
+    }
+
     // Detect another body
     pub fn detect(&self, b2: &Body, dist: f64, angle: f64) -> f64 {
         let d = self.position.dist_sq(&b2.position);
@@ -77,7 +146,7 @@ impl Body {
             return 0.0;
         }
 
-        if (self.position.atan2(&b2.position) - self.theta).abs() > angle {
+        if angle_between(self.position.atan2(&b2.position), self.theta).abs() > angle {
             return 0.0;
         }
 
@@ -97,12 +166,149 @@ impl Default for Body {
             },
             radius: 0.5,
             theta: random(),
+            velocity: (0.0, 0.0),
+            carrying: 0.0,
             emits: vec![],
             history: std::collections::VecDeque::new(),
         }
     }
 }
 
+//////////////////////////////////
+/// Vision {{{1
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Seen {
+    Nothing,
+    Food,
+    Creature,
+    Nest,
+    Wall,
+}
+
+impl Seen {
+    /// Network input for a kind of object
+    pub fn value(&self) -> f64 {
+        match self {
+            Seen::Nothing => 0.0,
+            Seen::Food => 1.0,
+            Seen::Creature => -1.0,
+            Seen::Nest => -0.5,
+            Seen::Wall => 0.5,
+        }
+    }
+}
+
+/// Rays spread over a field of view around the heading
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vision {
+    pub rays: usize,
+    pub fov: f64,
+    pub range: f64,
+    /// Closeness and kind of what each ray sees
+    pub readings: Vec<f64>,
+}
+
+impl Default for Vision {
+    fn default() -> Self {
+        Self::new(5, std::f64::consts::FRAC_PI_2, 10.0)
+    }
+}
+
+impl Vision {
+    pub fn new(rays: usize, fov: f64, range: f64) -> Self {
+        Self {
+            rays,
+            fov,
+            range,
+            readings: vec![0.0; 2 * rays],
+        }
+    }
+
+    /// Number of network inputs
+    pub fn inputs(&self) -> usize {
+        2 * self.rays
+    }
+
+    /// Heading of each ray
+    pub fn angles(&self, theta: f64) -> Vec<f64> {
+        (0..self.rays)
+            .map(|i| theta - self.fov / 2.0 + self.fov * (i as f64 + 0.5) / self.rays as f64)
+            .collect()
+    }
+
+    /// Cast the rays from a body, stopping at the bounds and the walls of the map
+    pub fn look(
+        &mut self,
+        entity: Entity,
+        body: &Body,
+        index: &SpatialIndex,
+        bounds: &Rect,
+        map: Option<&Map>,
+    ) {
+        let p = &body.position;
+        self.readings = self
+            .angles(body.theta)
+            .into_iter()
+            .flat_map(|theta| {
+                let mut wall = wall_distance(p, theta, bounds);
+                if let Some(d) = map.and_then(|m| m.ray(p, theta, self.range, bounds)) {
+                    wall = wall.min(d);
+                }
+                let (d, seen) = match index.cast_ray(p, theta, self.range, entity) {
+                    Some((d, item)) if d < wall => (
+                        d,
+                        match item.body_type {
+                            BodyType::Food => Seen::Food,
+                            BodyType::Creature => Seen::Creature,
+                            BodyType::Nest => Seen::Nest,
+                            BodyType::Obstacle => Seen::Wall,
+                        },
+                    ),
+                    _ if wall < self.range => (wall, Seen::Wall),
+                    _ => (self.range, Seen::Nothing),
+                };
+                vec![1.0 - d / self.range, seen.value()]
+            })
+            .collect();
+    }
+}
+
+/// Distance along a heading to the edge of the bounds
+fn wall_distance(p: &Position, theta: f64, bounds: &Rect) -> f64 {
+    let (dx, dy) = (theta.sin(), theta.cos());
+    let tx = if dx > 0.0 {
+        (bounds.right() as f64 - p.x) / dx
+    } else if dx < 0.0 {
+        (bounds.left() as f64 - p.x) / dx
+    } else {
+        f64::INFINITY
+    };
+    let ty = if dy > 0.0 {
+        (bounds.bottom() as f64 - p.y) / dy
+    } else if dy < 0.0 {
+        (bounds.top() as f64 - p.y) / dy
+    } else {
+        f64::INFINITY
+    };
+    tx.min(ty).max(0.0)
+}
+
+//////////////////////////////////
+/// Colony {{{1
+/// Colony a creature or nest belongs to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Colony(pub usize);
+
+//////////////////////////////////
+/// Store {{{1
+/// Food stored in a nest
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Store {
+    pub energy: f64,
+    pub deliveries: usize,
+}
+
 //////////////////////////////////
 /// Energy {{{ 1
 ///
@@ -132,24 +338,13 @@ pub struct Network {
     pub state: Vec<f64>,
 }
 
-impl Default for Network {
-    fn default() -> Self {
-        Self {
-            inputs: 2,
-            outputs: 2,
-            network: NeatNetwork::random(2, 2),
-            state: vec![0.0; 2],
-        }
-    }
-}
-
 impl Network {
-    pub fn new(genome: NeatGenome) -> Self {
+    pub fn new(genome: NeatGenome, inputs: usize, outputs: usize) -> Self {
         Self {
-            inputs: 2,
-            outputs: 2,
+            inputs: inputs as i32,
+            outputs: outputs as i32,
             network: NeatNetwork::from(genome),
-            state: vec![0.0; 2],
+            state: vec![0.0; outputs],
         }
     }
 }
@@ -186,8 +381,10 @@ mod test {
 
     #[test]
     fn test_detect() {
+        // Facing along x
         let b1 = Body {
             position: Position { x: 0.0, y: 0.0 },
+            theta: std::f64::consts::FRAC_PI_2,
             ..Default::default()
         };
 
@@ -197,6 +394,67 @@ mod test {
         };
 
         assert_eq!(b1.detect(&b2, 1.1, 0.01), 1.0);
+        assert_eq!(b1.detect(&b2, 0.9, 0.01), 0.0);
+
+        // Facing along y
+        let b1 = Body { theta: 0.0, ..b1 };
         assert_eq!(b1.detect(&b2, 1.1, 0.01), 0.0);
     }
+
+    #[test]
+    fn test_collisions() {
+        let mut b = Body {
+            position: Position { x: 1.0, y: 5.0 },
+            radius: 0.5,
+            velocity: (-2.0, 1.0),
+            ..Default::default()
+        };
+
+        let (normal, depth) = b.overlap(&Position { x: 1.0, y: 4.0 }, 1.0).unwrap();
+        assert_eq!(normal, (0.0, 1.0));
+        assert!((depth - 0.5).abs() < 1e-9);
+        assert!(b.overlap(&Position { x: 3.0, y: 5.0 }, 1.0).is_none());
+
+        // Sliding along the left wall keeps the speed along it
+        let bounds = Rect::new(0, 0, 10, 10);
+        b.position.x = 0.2;
+        b.collide_bounds(&bounds, Walls::Slide);
+        assert_eq!(b.position.x, 0.5);
+        assert_eq!(b.velocity, (0.0, 1.0));
+
+        // Bouncing off the bottom
+        b.position.y = 10.0;
+        b.collide_bounds(&bounds, Walls::Bounce(0.5));
+        assert_eq!(b.position.y, 9.5);
+        assert_eq!(b.velocity, (0.0, -0.5));
+    }
+
+    #[test]
+    fn test_vision() {
+        let bounds = Rect::new(0, 0, 100, 100);
+        let mut world = legion::World::default();
+        let body = Body {
+            position: Position { x: 50.0, y: 95.0 },
+            theta: std::f64::consts::FRAC_PI_4,
+            ..Default::default()
+        };
+        let food = Body {
+            position: Position { x: 53.0, y: 95.0 },
+            body_type: BodyType::Food,
+            ..Default::default()
+        };
+        let e1 = world.push((body.clone(),));
+        let e2 = world.push((food.clone(),));
+        let mut index = SpatialIndex::new(&bounds, 5.0);
+        index.rebuild(vec![(&e1, &body, None), (&e2, &food, None)]);
+
+        // One ray along y sees the wall, one along x the food
+        let mut vision = Vision::new(2, std::f64::consts::PI, 10.0);
+        vision.look(e1, &body, &index, &bounds, None);
+        assert_eq!(vision.readings.len(), 4);
+        assert_eq!(vision.readings[1], Seen::Wall.value());
+        assert_eq!(vision.readings[3], Seen::Food.value());
+        assert!((vision.readings[2] - 0.75).abs() < 1e-9);
+        assert!((vision.readings[0] - 0.5).abs() < 1e-9);
+    }
 }
@@ -1,31 +1,131 @@
-use crate::breeder::AntGenome;
+use crate::breeder::{AntBreeder, AntGenome};
 use crate::components::*;
-use crate::resources::Config;
+use crate::resources::{Config, FOOD_SCENT};
 use crate::utils::*;
+use evo::{Breeder, Evolver};
 
-pub type CreatureTuple = (Body, Genetic<AntGenome>, Network);
+pub type CreatureTuple<G> = (Body, Genetic<G>, Network, Vision, Colony);
+
+//////////////////////////////////
+/// Population
+/// Breeder of a population evolving in the world
+pub trait Population: Breeder<Genome: Send + Sync + 'static> + Send + Sync + 'static {
+    /// Components of a colony's creature grown from a genome
+    fn spawn(
+        colony: Colony,
+        species_id: i32,
+        gene: Self::Genome,
+        c: &Config,
+    ) -> CreatureTuple<Self::Genome>;
+}
+
+//////////////////////////////////
+/// Colony Evolver
+/// Evolves the creatures of one colony of a population.
+/// Colonies of the same population each have their own evolver,
+/// told apart by the Colony component of their creatures.
+pub trait ColonyEvolver: Evolver<Breeder: Population> + Send + Sync + 'static {}
+
+impl<E> ColonyEvolver for E where E: Evolver<Breeder: Population> + Send + Sync + 'static {}
+
+/// Genome of the creatures of an evolver
+pub type GenomeOf<E> = <<E as Evolver>::Breeder as Breeder>::Genome;
+
+impl Population for AntBreeder {
+    fn spawn(
+        colony: Colony,
+        species_id: i32,
+        gene: AntGenome,
+        c: &Config,
+    ) -> CreatureTuple<AntGenome> {
+        (
+            Body {
+                position: c.random_position(),
+                ..Body::random(&c.bounds).color((&gene.color).into())
+            },
+            Genetic::<AntGenome>::new(species_id, gene.clone()),
+            Network::new(gene.network, c.inputs(), c.outputs()),
+            c.vision.clone(),
+            colony,
+        )
+    }
+}
 
 pub struct Creature {}
 
 impl Creature {
-    pub fn new(species_id: i32, gene: AntGenome, c: &Config) -> CreatureTuple {
+    pub fn new(
+        colony: Colony,
+        species_id: i32,
+        gene: AntGenome,
+        c: &Config,
+    ) -> CreatureTuple<AntGenome> {
+        AntBreeder::spawn(colony, species_id, gene, c)
+    }
+}
+
+pub type NestTuple = (Body, Colony, Store);
+
+pub struct Nest {}
+
+impl Nest {
+    pub fn new(position: Position, colony: Colony) -> NestTuple {
         (
-            Body::random(&c.bounds).color((&gene.color).into()),
-            Genetic::<AntGenome>::new(species_id, gene.clone()),
-            Network::new(gene.network),
+            Body {
+                body_type: BodyType::Nest,
+                position,
+                radius: 3.0,
+                color: Color::rgb(0.6, 0.2, 0.4),
+                ..Default::default()
+            },
+            colony,
+            Store::default(),
         )
     }
 }
 
+pub type ObstacleTuple = (Body,);
+
+pub struct Obstacle {}
+
+impl Obstacle {
+    pub fn new(position: Position, radius: f64) -> ObstacleTuple {
+        (Body {
+            body_type: BodyType::Obstacle,
+            position,
+            radius,
+            color: Color::rgb(0.4, 0.4, 0.4),
+            energy: Energy {
+                amt: 0.0,
+                decay: 0.0,
+            },
+            ..Default::default()
+        },)
+    }
+}
+
 pub type FoodTuple = (Body,);
 
 pub struct Food {}
 
 impl Food {
+    /// Food emitting its scent with a strength
+    pub fn with_scent(strength: f64, c: &Config) -> FoodTuple {
+        let mut emits = vec![0.0; FOOD_SCENT + 1];
+        emits[FOOD_SCENT] = strength;
+        Self::new(emits, c)
+    }
+
     pub fn new(emits: Vec<f64>, c: &Config) -> FoodTuple {
-        (Body::random(&c.bounds)
+        let mut body = Body::random(&c.bounds)
             .body_type(BodyType::Food)
             .emits(emits)
-            .color(Color::rgb(1.0, 0.0, 0.0)),)
+            .color(Color::rgb(1.0, 0.0, 0.0));
+        body.position = c.food_position();
+        body.energy = Energy {
+            amt: c.food_energy,
+            decay: 0.0,
+        };
+        (body,)
     }
 }
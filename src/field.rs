@@ -2,6 +2,9 @@ use std::borrow::Cow;
 
 use crate::prelude::*;
 use crate::render::Vertex;
+use crate::map::{Map, Tile};
+use glium::texture::RawImage2d;
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
 use glium::{pixel_buffer::PixelBuffer, program, uniform, Surface, Texture2d};
 
 const QUAD: [Vertex; 4] = [
@@ -25,6 +28,8 @@ pub struct Field {
     vertices: glium::VertexBuffer<Vertex>,
     indices: glium::IndexBuffer<u16>,
     program: glium::Program,
+    /// Wall mask, red where pheromones can't spread
+    walls: Texture2d,
 }
 
 
@@ -55,6 +60,7 @@ impl Field {
 
         let texture = glium::Texture2d::empty_with_format(display, glium::texture::UncompressedFloatFormat::F32F32F32F32, glium::texture::MipmapsOption::NoMipmap, 1000, 1000).unwrap();
         let buffer = texture.read_to_pixel_buffer();
+        let walls = Texture2d::new(display, RawImage2d::from_raw_rgba(vec![0u8; 4], (1, 1))).unwrap();
 
         Field {
             texture,
@@ -62,9 +68,33 @@ impl Field {
             vertices,
             indices,
             program,
+            walls,
         }
     }
 
+    /// Block diffusion through the walls of a map, which are drawn with the field
+    pub fn set_walls(&mut self, display: &glium::Display, map: &Map) {
+        // Textures start with the bottom row, maps with the top one
+        let data: Vec<u8> = (0..map.height)
+            .rev()
+            .flat_map(|row| (0..map.width).map(move |col| (col, row)))
+            .flat_map(|(col, row)| match map.get(col, row) {
+                Tile::Wall => vec![255u8, 0, 0, 255],
+                _ => vec![0u8, 0, 0, 255],
+            })
+            .collect();
+        let image = RawImage2d::from_raw_rgba(data, (map.width as u32, map.height as u32));
+        self.walls = Texture2d::new(display, image).unwrap();
+    }
+
+    /// Walls sampled without blending tiles
+    fn walls(&self) -> glium::uniforms::Sampler<Texture2d> {
+        self.walls
+            .sampled()
+            .magnify_filter(MagnifySamplerFilter::Nearest)
+            .minify_filter(MinifySamplerFilter::Nearest)
+    }
+
     pub fn update(&mut self) {
         self.texture
             .as_surface()
@@ -72,7 +102,7 @@ impl Field {
                 &self.vertices,
                 &self.indices,
                 &self.program,
-                &uniform! {PASS_NUM: 0, TEX: &self.texture},
+                &uniform! {PASS_NUM: 0, TEX: &self.texture, WALLS: self.walls()},
                 &Default::default(),
             )
             .unwrap();
@@ -85,7 +115,7 @@ impl Field {
                 &self.vertices,
                 &self.indices,
                 &self.program,
-                &uniform! {PASS_NUM: 1, TEX: &self.texture},
+                &uniform! {PASS_NUM: 1, TEX: &self.texture, WALLS: self.walls()},
                 &Default::default(),
             )
             .unwrap();
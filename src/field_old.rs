@@ -1,3 +1,4 @@
+use crate::map::{Map, Tile};
 use ndarray::prelude::*;
 
 #[derive(Clone)]
@@ -8,6 +9,8 @@ pub struct Field {
     pub colors: Array<f64, Dim<[usize; 2]>>,
     pub decay: Array<f64, Dim<[usize; 1]>>,
     pub disperse: Array<f64, Dim<[usize; 1]>>,
+    /// 1 for open cells and 0 for walls
+    pub open: Array<f64, Dim<[usize; 2]>>,
     pub data: ndarray::Array<f64, Dim<[usize; 3]>>,
     pub derivative: ndarray::Array<f64, Dim<[usize; 4]>>,
 }
@@ -28,6 +31,7 @@ impl Field {
             colors,
             decay: Array::from_vec(decay),
             disperse: Array::from_vec(disperse),
+            open: Array::ones((h, w)),
             data: Array::zeros((h, w, slots)),
             derivative: Array::zeros((h, w, slots, 2)),
         }
@@ -52,16 +56,29 @@ impl Field {
         let mut padded: Array<f64, _> = Array::zeros((self.h + 2, self.w + 2, self.slots));
         padded.slice_mut(s![1..-1, 1..-1, ..]).assign(&self.data);
 
+        // Padded open cells, outside the field is open
+        let mut open: Array<f64, _> = Array::ones((self.h + 2, self.w + 2, 1));
+        open.slice_mut(s![1..-1, 1..-1, 0]).assign(&self.open);
+
         // take slices for each direction
         let left = padded.slice(s![..-2, 1..-1, ..]);
         let right = padded.slice(s![2.., 1..-1, ..]);
         let up = padded.slice(s![1..-1, ..-2, ..]);
         let down = padded.slice(s![1..-1, 2.., ..]);
 
+        // Walls reflect the cell's own value so nothing flows into them
+        let data = &self.data;
+        let through = |n: &ArrayView3<f64>, o: ArrayView3<f64>| n * &o + data * &(1.0 - &o);
+        let neighbours = through(&left, open.slice(s![..-2, 1..-1, ..]))
+            + through(&right, open.slice(s![2.., 1..-1, ..]))
+            + through(&up, open.slice(s![1..-1, ..-2, ..]))
+            + through(&down, open.slice(s![1..-1, 2.., ..]));
+
         // Next iteration
-        let new = (&left + &right + &up + &down) / 4.0 * (1.0 - disperse) + &self.data * disperse;
-        // Assign it
-        (new * decay).assign_to(&mut self.data);
+        let new = neighbours / 4.0 * (1.0 - disperse) + &self.data * disperse;
+        let own = open.slice(s![1..-1, 1..-1, ..]);
+        // Assign it, walls stay empty
+        (new * decay * &own).assign_to(&mut self.data);
 
         // Calculate derivative
         self.derivative.slice_mut(s![.., .., .., 0]).assign(&(&left - &right));
@@ -74,6 +91,24 @@ impl Field {
         &Array::from_vec(slots).assign_to(cells);
     }
 
+    /// Make a cell a wall, which pheromones can't spread into
+    pub fn set_wall(&mut self, row: usize, col: usize) {
+        self.open[(row, col)] = 0.0;
+        self.data.slice_mut(s![row, col, ..]).fill(0.0);
+    }
+
+    /// Make the cells under the walls of a map walls, the map is stretched over the field
+    pub fn set_walls(&mut self, map: &Map) {
+        for row in 0..self.h {
+            for col in 0..self.w {
+                let tile = map.get(col * map.width / self.w, row * map.height / self.h);
+                if tile == Tile::Wall {
+                    self.set_wall(row, col);
+                }
+            }
+        }
+    }
+
     /// Add to a cell
     pub fn add(&mut self, row: usize, col: usize, slots: Vec<f64>) {
         let mut cell = self.data.slice_mut(s![row, col, ..]);
@@ -145,6 +180,42 @@ mod tests {
         assert_eq!(r[1], 0.1);
     }
 
+    #[test]
+    fn test_walls() {
+        let mut field = Field::new(
+            10,
+            10,
+            vec![1.0; 1],
+            vec![0.5; 1],
+            array![[1.0, 0.0, 0.0]],
+        );
+
+        // A full wall across the middle column
+        for row in 0..10 {
+            field.set_wall(row, 5);
+        }
+        field.set(4, 2, vec![1.0]);
+        for _ in 0..50 {
+            field.update(0.1);
+        }
+
+        assert!(field.get(4, 4)[0] > 0.0);
+        assert_eq!(field.get(4, 5)[0], 0.0);
+        assert_eq!(field.get(4, 7)[0], 0.0);
+    }
+
+    #[test]
+    fn test_map_walls() {
+        let mut field = Field::new(10, 4, vec![1.0; 1], vec![0.5; 1], array![[1.0, 0.0, 0.0]]);
+
+        // Each tile covers two columns and rows
+        field.set_walls(&Map::parse("..#..\n..#..").unwrap());
+        assert_eq!(field.open.column(3).sum(), 4.0);
+        assert_eq!(field.open.column(4).sum(), 0.0);
+        assert_eq!(field.open.column(5).sum(), 0.0);
+        assert_eq!(field.open.sum(), 32.0);
+    }
+
     #[test]
     fn test_add() {
         let mut field = Field::new(
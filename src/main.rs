@@ -3,8 +3,11 @@ mod breeder;
 mod components;
 mod creature;
 mod field;
+mod map;
 mod prelude;
 mod resources;
+mod senses;
+mod spatial;
 mod systems;
 mod utils;
 mod render;
@@ -39,14 +42,25 @@ fn main() {
     // Combine into a display
     let mut display = glium::Display::new(window_builder, context_builder, &event_loop).unwrap();
 
-    // Create app
-    let mut app = App::new();
+    // Create app, with the map given as an argument and stats written with --stats
+    let mut args = std::env::args().skip(1);
+    let (mut map, mut stats) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stats" => stats = args.next().map(std::path::PathBuf::from),
+            path => map = Some(map::Map::load(path).expect("Could not load the map")),
+        }
+    }
+    let mut app = App::new(map, stats);
 
     // Create renderer
     let renderer = AppRenderable::new(&app, &display);
 
     // Field Renderer
     let mut field = field::Field::new(&display);
+    if let Some(map) = app.resources.get::<resources::Config>().and_then(|c| c.map.clone()) {
+        field.set_walls(&display, &map);
+    }
     app.resources.insert(field.to_arr());
 
     // Speed of sim
@@ -71,6 +85,7 @@ fn main() {
             glutin::event::Event::WindowEvent { event, .. } => {
                 match event {
                     glutin::event::WindowEvent::CloseRequested => {
+                        app.export_lineage();
                         *control_flow = glium::glutin::event_loop::ControlFlow::Exit;
                         return;
                     }
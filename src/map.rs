@@ -0,0 +1,393 @@
+use std::io;
+use std::path::Path;
+
+use crate::components::Body;
+use crate::resources::{Config, Walls};
+use crate::utils::*;
+use evo::utils::random_d;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tile {
+    Empty,
+    Wall,
+    Food,
+    Nest,
+}
+
+//////////////////////////////////
+/// Map
+/// Grid of tiles stretched over the bounds, row 0 is the top of the arena
+#[derive(Clone, Debug, PartialEq)]
+pub struct Map {
+    pub width: usize,
+    pub height: usize,
+    tiles: Vec<Tile>,
+}
+
+impl Map {
+    /// Empty map
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            tiles: vec![Tile::Empty; width * height],
+        }
+    }
+
+    /// Load a PNG image or a text grid, by extension
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => {
+                let image = image::open(path)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                Self::from_image(&image.to_rgba8()).check()
+            }
+            _ => Self::parse(&std::fs::read_to_string(path)?),
+        }
+    }
+
+    /// Text grid with `#` for walls, `F` for food, `N` for nests and `.` or spaces for
+    /// empty tiles, short lines are padded with empty tiles
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let lines: Vec<&str> = text.lines().filter(|l| !l.is_empty()).collect();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let mut map = Self::new(width, lines.len());
+
+        for (row, line) in lines.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                let tile = match c {
+                    '#' => Tile::Wall,
+                    'F' | 'f' => Tile::Food,
+                    'N' | 'n' => Tile::Nest,
+                    '.' | ' ' => Tile::Empty,
+                    c => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Unknown tile '{}' at line {} column {}", c, row + 1, col + 1),
+                        ))
+                    }
+                };
+                map.set(col, row, tile);
+            }
+        }
+        map.check()
+    }
+
+    /// Maps need open ground to place creatures and food on
+    fn check(self) -> io::Result<Self> {
+        if self.tiles.iter().all(|t| *t == Tile::Wall) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Map has no open tile",
+            ));
+        }
+        Ok(self)
+    }
+
+    /// One tile per pixel: dark pixels are walls, green ones food and blue ones nests
+    pub fn from_image(image: &image::RgbaImage) -> Self {
+        let mut map = Self::new(image.width() as usize, image.height() as usize);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let [r, g, b, a] = pixel.0;
+            let tile = if a < 128 {
+                Tile::Empty
+            } else if r.max(g).max(b) < 64 {
+                Tile::Wall
+            } else if g > 128 && g > r && g > b {
+                Tile::Food
+            } else if b > 128 && b > r && b > g {
+                Tile::Nest
+            } else {
+                Tile::Empty
+            };
+            map.set(x as usize, y as usize, tile);
+        }
+        map
+    }
+
+    /// Tile at a column and row, outside the map is empty
+    pub fn get(&self, col: usize, row: usize) -> Tile {
+        if col >= self.width || row >= self.height {
+            return Tile::Empty;
+        }
+        self.tiles[col + row * self.width]
+    }
+
+    pub fn set(&mut self, col: usize, row: usize, tile: Tile) {
+        self.tiles[col + row * self.width] = tile;
+    }
+
+    /// Columns and rows of the tiles that aren't walls
+    pub fn open(&self) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (col, row)))
+            .filter(|&(col, row)| self.get(col, row) != Tile::Wall)
+            .collect()
+    }
+
+    /// Columns and rows of the tiles of a kind
+    pub fn find(&self, tile: Tile) -> Vec<(usize, usize)> {
+        (0..self.height)
+            .flat_map(|row| (0..self.width).map(move |col| (col, row)))
+            .filter(|&(col, row)| self.get(col, row) == tile)
+            .collect()
+    }
+
+    /// Size of a tile in the world
+    pub fn cell(&self, bounds: &Rect) -> (f64, f64) {
+        (
+            bounds.width as f64 / self.width.max(1) as f64,
+            bounds.height as f64 / self.height.max(1) as f64,
+        )
+    }
+
+    /// Column and row under a position
+    pub fn tile_at(&self, p: &Position, bounds: &Rect) -> Option<(usize, usize)> {
+        let (w, h) = self.cell(bounds);
+        let col = ((p.x - bounds.left() as f64) / w).floor();
+        let row = ((p.y - bounds.top() as f64) / h).floor();
+        if col < 0.0 || row < 0.0 || col >= self.width as f64 || row >= self.height as f64 {
+            return None;
+        }
+        Some((col as usize, self.height - 1 - row as usize))
+    }
+
+    pub fn is_wall(&self, p: &Position, bounds: &Rect) -> bool {
+        match self.tile_at(p, bounds) {
+            Some((col, row)) => self.get(col, row) == Tile::Wall,
+            None => false,
+        }
+    }
+
+    /// Centre of a tile in the world
+    pub fn center(&self, col: usize, row: usize, bounds: &Rect) -> Position {
+        let (w, h) = self.cell(bounds);
+        Position {
+            x: bounds.left() as f64 + (col as f64 + 0.5) * w,
+            y: bounds.top() as f64 + ((self.height - 1 - row) as f64 + 0.5) * h,
+        }
+    }
+
+    /// Push a body out of the wall tiles it overlaps, hitting them
+    pub fn collide(&self, body: &mut Body, bounds: &Rect, walls: Walls) {
+        let (w, h) = self.cell(bounds);
+        let r = body.radius;
+        let p = body.position;
+        let (x0, y0) = (bounds.left() as f64, bounds.top() as f64);
+
+        // Tiles under the bounding box of the body, counting rows from the bottom
+        let cols = ((p.x - r - x0) / w).floor().max(0.0) as usize
+            ..=((p.x + r - x0) / w).floor().max(0.0) as usize;
+        let rows = ((p.y - r - y0) / h).floor().max(0.0) as usize
+            ..=((p.y + r - y0) / h).floor().max(0.0) as usize;
+
+        for i in rows {
+            for col in cols.clone() {
+                if i >= self.height || self.get(col, self.height - 1 - i) != Tile::Wall {
+                    continue;
+                }
+
+                let (left, right) = (x0 + col as f64 * w, x0 + (col + 1) as f64 * w);
+                let (top, bottom) = (y0 + i as f64 * h, y0 + (i + 1) as f64 * h);
+                let p = body.position;
+
+                // Closest point of the tile
+                let cx = p.x.clamp(left, right);
+                let cy = p.y.clamp(top, bottom);
+                let (dx, dy) = (p.x - cx, p.y - cy);
+                let d2 = dx * dx + dy * dy;
+                if d2 >= r * r {
+                    continue;
+                }
+
+                let (normal, depth) = if d2 > 1e-18 {
+                    let d = d2.sqrt();
+                    ((dx / d, dy / d), r - d)
+                } else {
+                    // Centre inside the tile, leave by the closest side
+                    [
+                        ((-1.0, 0.0), p.x - left),
+                        ((1.0, 0.0), right - p.x),
+                        ((0.0, -1.0), p.y - top),
+                        ((0.0, 1.0), bottom - p.y),
+                    ]
+                    .iter()
+                    .cloned()
+                    .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                    .map(|(n, d)| (n, d + r))
+                    .unwrap()
+                };
+
+                body.position += (normal.0 * depth, normal.1 * depth);
+                body.hit(normal, walls);
+            }
+        }
+    }
+
+    /// Distance along a ray to the first wall tile within range
+    pub fn ray(&self, p: &Position, theta: f64, range: f64, bounds: &Rect) -> Option<f64> {
+        let (w, h) = self.cell(bounds);
+        let step = w.min(h) / 4.0;
+        let (dx, dy) = (theta.sin(), theta.cos());
+
+        let mut t = 0.0;
+        while t <= range {
+            let q = Position {
+                x: p.x + t * dx,
+                y: p.y + t * dy,
+            };
+            if self.is_wall(&q, bounds) {
+                return Some(t);
+            }
+            t += step;
+        }
+        None
+    }
+}
+
+impl Config {
+    /// Random position outside the walls, anywhere if the map is all walls
+    pub fn random_position(&self) -> Position {
+        let map = match &self.map {
+            Some(map) => map,
+            None => return Position::random(&self.bounds),
+        };
+
+        // Mostly open maps rarely miss, others pick among the open tiles
+        for _ in 0..100 {
+            let p = Position::random(&self.bounds);
+            if !map.is_wall(&p, &self.bounds) {
+                return p;
+            }
+        }
+
+        let open = map.open();
+        if open.is_empty() {
+            return Position::random(&self.bounds);
+        }
+        let (col, row) = open[evo::utils::random_i(open.len())];
+        let (w, h) = map.cell(&self.bounds);
+        map.center(col, row, &self.bounds) + (random_d(w / 2.0), random_d(h / 2.0))
+    }
+
+    /// Random spot in a food source of the map, anywhere open without one
+    pub fn food_position(&self) -> Position {
+        if let Some(map) = &self.map {
+            let sources = map.find(Tile::Food);
+            if !sources.is_empty() {
+                let (col, row) = sources[evo::utils::random_i(sources.len())];
+                let (w, h) = map.cell(&self.bounds);
+                return map.center(col, row, &self.bounds) + (random_d(w / 2.0), random_d(h / 2.0));
+            }
+        }
+        self.random_position()
+    }
+
+    /// Keep a body out of the walls of the bounds and map
+    pub fn collide(&self, body: &mut Body) {
+        let walls = self.physics.walls;
+        if let Some(map) = &self.map {
+            map.collide(body, &self.bounds, walls);
+        }
+        body.collide_bounds(&self.bounds, walls);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BRIDGE: &str = "
+#####
+N.#.F
+.....
+";
+
+    #[test]
+    fn test_parse() {
+        let map = Map::parse(BRIDGE).unwrap();
+        assert_eq!((map.width, map.height), (5, 3));
+        assert_eq!(map.get(2, 1), Tile::Wall);
+        assert_eq!(map.find(Tile::Nest), vec![(0, 1)]);
+        assert_eq!(map.find(Tile::Food), vec![(4, 1)]);
+        assert!(Map::parse("#?#").is_err());
+        assert!(Map::parse("##\n##").is_err());
+
+        // Pixels give the same tiles
+        let mut img = image::RgbaImage::from_pixel(5, 3, image::Rgba([255, 255, 255, 255]));
+        for col in 0..5 {
+            img.put_pixel(col, 0, image::Rgba([0, 0, 0, 255]));
+        }
+        img.put_pixel(2, 1, image::Rgba([0, 0, 0, 255]));
+        img.put_pixel(0, 1, image::Rgba([0, 0, 255, 255]));
+        img.put_pixel(4, 1, image::Rgba([0, 255, 0, 255]));
+        assert_eq!(Map::from_image(&img), map);
+    }
+
+    #[test]
+    fn test_world() {
+        let map = Map::parse(BRIDGE).unwrap();
+        let bounds = Rect::new(0, 0, 50, 30);
+
+        // The top row of the map is the top of the arena
+        assert!(map.is_wall(&Position { x: 5.0, y: 25.0 }, &bounds));
+        assert!(!map.is_wall(&Position { x: 5.0, y: 5.0 }, &bounds));
+        assert_eq!(map.center(4, 1, &bounds), Position { x: 45.0, y: 15.0 });
+
+        // Looking along x from the nest hits the middle wall
+        let d = map.ray(&Position { x: 5.0, y: 15.0 }, std::f64::consts::FRAC_PI_2, 30.0, &bounds);
+        assert!((d.unwrap() - 15.0).abs() < 1.0);
+        assert!(map.ray(&Position { x: 5.0, y: 5.0 }, std::f64::consts::FRAC_PI_2, 30.0, &bounds).is_none());
+
+        // Walking into the middle wall slides along it
+        let mut body = Body {
+            position: Position { x: 19.5, y: 15.0 },
+            radius: 1.0,
+            velocity: (2.0, 1.0),
+            ..Default::default()
+        };
+        map.collide(&mut body, &bounds, Walls::Slide);
+        assert!((body.position.x - 19.0).abs() < 1e-9);
+        assert_eq!(body.velocity, (0.0, 1.0));
+    }
+
+    #[test]
+    fn test_random_position() {
+        // A single open tile among walls
+        let mut map = Map::parse("#####\n##.##\n#####").unwrap();
+        let config = Config {
+            bounds: Rect::new(0, 0, 50, 30),
+            map: Some(map.clone()),
+            ..Config::default()
+        };
+        for _ in 0..10 {
+            let p = config.random_position();
+            assert_eq!(map.tile_at(&p, &config.bounds), Some((2, 1)));
+        }
+
+        // All walls still gives a position
+        map.set(2, 1, Tile::Wall);
+        let config = Config {
+            map: Some(map),
+            ..config
+        };
+        config.random_position();
+    }
+
+    #[test]
+    fn test_food_position() {
+        // Food spreads over its tile instead of stacking
+        let map = Map::parse("###\n#F.\n###").unwrap();
+        let config = Config {
+            bounds: Rect::new(0, 0, 30, 30),
+            map: Some(map.clone()),
+            ..Config::default()
+        };
+        let spots: Vec<_> = (0..10).map(|_| config.food_position()).collect();
+        for p in spots.iter() {
+            assert_eq!(map.tile_at(p, &config.bounds), Some((1, 1)));
+        }
+        assert!(spots.iter().any(|p| *p != spots[0]));
+    }
+}
@@ -1,4 +1,8 @@
+use crate::components::{Colony, Vision};
+use crate::map::Map;
+use crate::senses::{Actuator, Sensor};
 use crate::utils::Rect;
+use std::collections::HashMap;
 use std::f64::consts;
 
 //////////////////////////////////
@@ -31,11 +35,182 @@ impl Time {
     }
 }
 
+/// Field channel of the scent of food, ants lay pheromones on the others
+pub const FOOD_SCENT: usize = 0;
+
+//////////////////////////////////
+/// Heading based movement
+///
+#[derive(Clone, Copy, Debug)]
+pub struct Locomotion {
+    pub max_speed: f64,
+    /// Turning rate at full output, radians per second
+    pub max_turn: f64,
+    /// Acceleration at full thrust
+    pub thrust: f64,
+    /// Fraction of velocity lost per second
+    pub drag: f64,
+    /// Energy per second at full thrust
+    pub thrust_cost: f64,
+}
+
+impl Default for Locomotion {
+    fn default() -> Self {
+        Self {
+            max_speed: 5.0,
+            max_turn: consts::PI,
+            thrust: 10.0,
+            drag: 2.0,
+            thrust_cost: 0.05,
+        }
+    }
+}
+
+/// What happens to the velocity of a body hitting a wall or obstacle
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Walls {
+    /// Keep moving along the wall
+    Slide,
+    /// Reflect with a restitution, 1 keeps all the speed
+    Bounce(f64),
+}
+
+//////////////////////////////////
+/// Collisions between bodies and with walls
+///
+#[derive(Clone, Copy, Debug)]
+pub struct Physics {
+    pub walls: Walls,
+    /// Fraction of the overlap of creatures resolved per tick, 0 lets them overlap
+    pub push: f64,
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self {
+            walls: Walls::Slide,
+            push: 0.5,
+        }
+    }
+}
+
+//////////////////////////////////
+/// In-world reproduction
+/// Creatures breed when they have enough energy instead of being replaced on death
+#[derive(Clone, Copy, Debug)]
+pub struct Reproduction {
+    /// Energy needed to breed
+    pub energy: f64,
+    /// Energy handed from the parent to its offspring
+    pub cost: f64,
+    /// Distance to look for a mate, alone the parent mutates
+    pub mate_radius: f64,
+    /// Dead creatures are replaced from the pool below this population of a colony
+    pub min_population: usize,
+    pub max_population: usize,
+}
+
+impl Default for Reproduction {
+    fn default() -> Self {
+        Self {
+            energy: 3.0,
+            cost: 1.5,
+            mate_radius: 5.0,
+            min_population: 10,
+            max_population: 100,
+        }
+    }
+}
+
+//////////////////////////////////
+/// Colonies
+/// Evolver of every colony of a population, by colony id
+pub struct Colonies<E> {
+    evolvers: HashMap<Colony, E>,
+}
+
+impl<E> Default for Colonies<E> {
+    fn default() -> Self {
+        Self {
+            evolvers: HashMap::new(),
+        }
+    }
+}
+
+impl<E> Colonies<E> {
+    pub fn insert(&mut self, colony: Colony, evolver: E) {
+        self.evolvers.insert(colony, evolver);
+    }
+
+    pub fn get(&self, colony: Colony) -> Option<&E> {
+        self.evolvers.get(&colony)
+    }
+
+    pub fn get_mut(&mut self, colony: Colony) -> Option<&mut E> {
+        self.evolvers.get_mut(&colony)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Colony, &E)> {
+        self.evolvers.iter()
+    }
+}
+
 //////////////////////////////////
 /// World Config
 ///
 pub struct Config {
     pub bounds: Rect,
+    /// Walls, food sources and nests stretched over the bounds
+    pub map: Option<Map>,
+
+    /// Energy content of a full food item
+    pub food_energy: f64,
+    /// Energy regrown by a food item per second
+    pub food_regrow: f64,
+    /// Energy eaten from a food item per second
+    pub bite: f64,
+    /// Food a creature can carry to its nest
+    pub carry: f64,
+
+    /// Pheromone emitted at full output and its energy per second
+    pub emit_strength: f64,
+    pub emit_cost: f64,
+
+    /// Vision of new creatures
+    pub vision: Vision,
+
+    pub locomotion: Locomotion,
+    pub physics: Physics,
+
+    /// Embodied evolution, the pool only archives the dead when set
+    pub reproduction: Option<Reproduction>,
+
+    /// Body plan, sensors are network inputs and actuators outputs in order
+    pub sensors: Vec<Sensor>,
+    pub actuators: Vec<Actuator>,
 }
 
 
+
+/// Small empty world for tests
+#[cfg(test)]
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bounds: Rect::new(0, 0, 100, 100),
+            map: None,
+            food_energy: 1.0,
+            food_regrow: 0.0,
+            bite: 1.0,
+            carry: 1.0,
+            emit_strength: 1.0,
+            emit_cost: 0.0,
+            vision: Vision::default(),
+            locomotion: Locomotion::default(),
+            physics: Physics::default(),
+            reproduction: None,
+            sensors: vec![],
+            actuators: vec![],
+        }
+    }
+}
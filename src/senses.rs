@@ -0,0 +1,250 @@
+use crate::components::{Body, Colony, Vision};
+use crate::field::FieldArr;
+use crate::resources::{Config, Time};
+use crate::spatial::{angle_between, SpatialIndex};
+use crate::utils::*;
+
+/// What a creature's sensors can read in a tick
+pub struct SenseContext<'a> {
+    pub body: &'a Body,
+    pub colony: Colony,
+    pub vision: Option<&'a Vision>,
+    pub field: &'a FieldArr,
+    pub time: &'a Time,
+    pub config: &'a Config,
+    pub index: &'a SpatialIndex,
+}
+
+impl<'a> SenseContext<'a> {
+    /// Position of the body in field coordinates
+    pub fn field_position(&self) -> (f64, f64) {
+        let bounds = &self.config.bounds;
+        (
+            self.body.position.x / bounds.width as f64,
+            1.0 - self.body.position.y / bounds.height as f64,
+        )
+    }
+}
+
+//////////////////////////////////
+/// Sensor
+/// Network inputs read from the world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sensor {
+    /// Field gradient of a colour channel along x and y
+    Gradient(usize),
+    /// Field value of a colour channel
+    Scent(usize),
+    /// Readings of the vision rays
+    Vision,
+    /// Stored energy
+    Energy,
+    /// Sine and cosine of the heading
+    Heading,
+    /// Sine wave with a period in seconds
+    Clock(f64),
+    /// Sine and cosine of the direction to the colony's nearest nest relative to the heading
+    Nest,
+    /// Food being carried
+    Carrying,
+    Constant(f64),
+}
+
+impl Sensor {
+    /// Number of network inputs
+    pub fn size(&self, config: &Config) -> usize {
+        match self {
+            Sensor::Gradient(_) | Sensor::Heading | Sensor::Nest => 2,
+            Sensor::Vision => config.vision.inputs(),
+            _ => 1,
+        }
+    }
+
+    /// Append the readings to the inputs
+    pub fn read(&self, ctx: &SenseContext, inputs: &mut Vec<f64>) {
+        let (x, y) = ctx.field_position();
+        match *self {
+            Sensor::Gradient(c) => {
+                let (cx, cy) = ctx.field.get_dx(x, y);
+                inputs.push(channel(&cx, c));
+                inputs.push(channel(&cy, c));
+            }
+            Sensor::Scent(c) => inputs.push(channel(&ctx.field.get(x, y), c)),
+            Sensor::Vision => match ctx.vision {
+                Some(v) => inputs.extend(v.readings.iter()),
+                None => inputs.extend(vec![0.0; self.size(ctx.config)]),
+            },
+            Sensor::Energy => inputs.push(ctx.body.energy.amt),
+            Sensor::Heading => {
+                inputs.push(ctx.body.theta.sin());
+                inputs.push(ctx.body.theta.cos());
+            }
+            Sensor::Clock(period) => inputs.push(ctx.time.sin(period, 0.0)),
+            Sensor::Nest => match ctx.index.nearest_nest(&ctx.body.position, ctx.colony) {
+                Some(nest) => {
+                    let p = &ctx.body.position;
+                    let angle = angle_between(p.atan2(&nest.position), ctx.body.theta);
+                    inputs.push(angle.sin());
+                    inputs.push(angle.cos());
+                }
+                None => inputs.extend(vec![0.0; 2]),
+            },
+            Sensor::Carrying => inputs.push(ctx.body.carrying),
+            Sensor::Constant(v) => inputs.push(v),
+        }
+    }
+}
+
+/// Colour channel by index, in the order emissions become colours
+fn channel(c: &Color, i: usize) -> f64 {
+    match i {
+        0 => c.r,
+        1 => c.b,
+        2 => c.g,
+        _ => c.a,
+    }
+}
+
+//////////////////////////////////
+/// Actuator
+/// Network outputs applied to the body
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Actuator {
+    /// Move along x and y
+    Velocity,
+    /// Change the heading
+    Turn,
+    /// Move along the heading
+    Forward,
+    /// Turning rate and forward thrust of the locomotion model
+    Drive,
+    /// Pheromone emission on a field channel
+    Emit(usize),
+}
+
+impl Actuator {
+    /// Number of network outputs
+    pub fn size(&self) -> usize {
+        match self {
+            Actuator::Velocity | Actuator::Drive => 2,
+            _ => 1,
+        }
+    }
+
+    /// Apply the actuator's outputs to the body
+    pub fn apply(&self, out: &[f64], body: &mut Body, dt: f64, config: &Config) {
+        match self {
+            Actuator::Velocity => {
+                body.position.x += out[0] * dt;
+                body.position.y += out[1] * dt;
+            }
+            Actuator::Turn => body.theta += out[0] * dt,
+            Actuator::Forward => body.position = body.position.advance(out[0] * dt, body.theta),
+            Actuator::Drive => {
+                let l = &config.locomotion;
+                let thrust = out[1].clamp(0.0, 1.0);
+                body.theta += out[0].clamp(-1.0, 1.0) * l.max_turn * dt;
+
+                // Accelerate along the heading
+                let a = thrust * l.thrust * dt;
+                body.velocity.0 += a * body.theta.sin();
+                body.velocity.1 += a * body.theta.cos();
+                body.energy.amt -= thrust * l.thrust_cost * dt;
+            }
+            Actuator::Emit(c) => {
+                let amt = out[0].clamp(0.0, 1.0);
+                if body.emits.len() <= *c {
+                    body.emits.resize(c + 1, 0.0);
+                }
+                body.emits[*c] = amt * config.emit_strength;
+                body.energy.amt -= amt * config.emit_cost * dt;
+            }
+        }
+    }
+}
+
+impl Config {
+    /// Number of network inputs of the sensors
+    pub fn inputs(&self) -> usize {
+        self.sensors.iter().map(|s| s.size(self)).sum()
+    }
+
+    /// Number of network outputs of the actuators
+    pub fn outputs(&self) -> usize {
+        self.actuators.iter().map(|a| a.size()).sum()
+    }
+
+    /// Network inputs of a creature
+    pub fn sense(&self, ctx: &SenseContext) -> Vec<f64> {
+        let mut inputs = Vec::with_capacity(self.inputs());
+        for sensor in self.sensors.iter() {
+            sensor.read(ctx, &mut inputs);
+        }
+        inputs
+    }
+
+    /// Apply network outputs to a body, missing outputs count as 0
+    /// Emissions only last for the tick they are set
+    pub fn act(&self, out: &[f64], body: &mut Body, dt: f64) {
+        body.emits.iter_mut().for_each(|e| *e = 0.0);
+
+        let mut out = out.to_vec();
+        out.resize(out.len().max(self.outputs()), 0.0);
+
+        let mut rest = &out[..];
+        for actuator in self.actuators.iter() {
+            let (now, next) = rest.split_at(actuator.size());
+            actuator.apply(now, body, dt, self);
+            rest = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_act() {
+        let config = Config {
+            emit_cost: 0.1,
+            vision: Vision::new(3, 1.0, 10.0),
+            sensors: vec![Sensor::Gradient(0), Sensor::Vision, Sensor::Energy],
+            actuators: vec![Actuator::Turn, Actuator::Velocity],
+            ..Config::default()
+        };
+        assert_eq!(config.inputs(), 9);
+        assert_eq!(config.outputs(), 3);
+
+        let mut body = Body {
+            position: Position { x: 10.0, y: 10.0 },
+            theta: 0.0,
+            ..Default::default()
+        };
+        config.act(&[1.0, 2.0], &mut body, 0.5);
+        assert_eq!(body.theta, 0.5);
+        assert_eq!(body.position, Position { x: 11.0, y: 10.0 });
+
+        // Full thrust along the heading
+        let config = Config {
+            actuators: vec![Actuator::Drive],
+            ..config
+        };
+        body.theta = std::f64::consts::FRAC_PI_2;
+        let energy = body.energy.amt;
+        config.act(&[0.0, 1.0], &mut body, 0.1);
+        assert!(body.velocity.0 > 0.0);
+        assert!(body.velocity.1.abs() < 1e-9);
+        assert!(body.energy.amt < energy);
+
+        // Emission costs energy
+        let config = Config {
+            actuators: vec![Actuator::Emit(2)],
+            ..config
+        };
+        let energy = body.energy.amt;
+        config.act(&[0.5], &mut body, 0.1);
+        assert_eq!(body.emits, vec![0.0, 0.0, 0.5]);
+        assert!(body.energy.amt < energy);
+    }
+}
@@ -0,0 +1,257 @@
+use crate::components::{Body, BodyType, Colony};
+use crate::utils::*;
+use legion::*;
+
+/// A body as seen by the index
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Item {
+    pub entity: Entity,
+    pub position: Position,
+    pub radius: f64,
+    pub body_type: BodyType,
+    pub colony: Option<Colony>,
+}
+
+//////////////////////////////////
+/// Spatial Index
+/// Uniform grid of bodies rebuilt every tick
+pub struct SpatialIndex {
+    cell: f64,
+    cols: usize,
+    rows: usize,
+    cells: Vec<Vec<Item>>,
+    /// Largest radius in the grid, to find bodies overlapping from other cells
+    max_radius: f64,
+    /// Nests, which are sensed from anywhere
+    nests: Vec<Item>,
+}
+
+impl SpatialIndex {
+    /// New grid covering the bounds with square cells
+    pub fn new(bounds: &Rect, cell: f64) -> Self {
+        let cols = (bounds.width as f64 / cell).ceil().max(1.0) as usize;
+        let rows = (bounds.height as f64 / cell).ceil().max(1.0) as usize;
+        Self {
+            cell,
+            cols,
+            rows,
+            cells: vec![vec![]; cols * rows],
+            max_radius: 0.0,
+            nests: vec![],
+        }
+    }
+
+    /// Replace the contents of the grid
+    pub fn rebuild<'a, I>(&mut self, bodies: I)
+    where
+        I: IntoIterator<Item = (&'a Entity, &'a Body, Option<&'a Colony>)>,
+    {
+        self.cells.iter_mut().for_each(|c| c.clear());
+        self.nests.clear();
+        self.max_radius = 0.0;
+        for (entity, body, colony) in bodies {
+            self.insert(Item {
+                entity: *entity,
+                position: body.position,
+                radius: body.radius,
+                body_type: body.body_type,
+                colony: colony.copied(),
+            });
+        }
+    }
+
+    pub fn insert(&mut self, item: Item) {
+        let (col, row) = self.cell_of(&item.position);
+        self.max_radius = self.max_radius.max(item.radius);
+        self.cells[col + row * self.cols].push(item);
+        if item.body_type == BodyType::Nest {
+            self.nests.push(item);
+        }
+    }
+
+    /// Closest nest of a colony to a position
+    pub fn nearest_nest(&self, p: &Position, colony: Colony) -> Option<&Item> {
+        self.nests.iter().filter(|n| n.colony == Some(colony)).min_by(|a, b| {
+            a.position
+                .dist_sq(p)
+                .partial_cmp(&b.position.dist_sq(p))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+
+    /// Bodies overlapping a circle
+    pub fn query_radius(&self, p: &Position, r: f64) -> Vec<&Item> {
+        self.candidates(p, r)
+            .filter(|i| {
+                let d = r + i.radius;
+                i.position.dist_sq(p) < d * d
+            })
+            .collect()
+    }
+
+    /// Bodies overlapping a circle within an angle of a heading
+    pub fn query_cone(&self, p: &Position, r: f64, theta: f64, angle: f64) -> Vec<&Item> {
+        self.query_radius(p, r)
+            .into_iter()
+            .filter(|i| {
+                i.position == *p || angle_between(p.atan2(&i.position), theta).abs() <= angle
+            })
+            .collect()
+    }
+
+    /// Nearest body hit by a ray with its distance, ignoring one entity
+    pub fn cast_ray(
+        &self,
+        p: &Position,
+        theta: f64,
+        range: f64,
+        ignore: Entity,
+    ) -> Option<(f64, &Item)> {
+        let (dx, dy) = (theta.sin(), theta.cos());
+        self.query_radius(p, range)
+            .into_iter()
+            .filter(|i| i.entity != ignore)
+            .filter_map(|i| {
+                // Distance along the ray to the closest point and from the ray to the centre
+                let (cx, cy) = (i.position.x - p.x, i.position.y - p.y);
+                let r2 = i.radius * i.radius;
+                if cx * cx + cy * cy <= r2 {
+                    return Some((0.0, i));
+                }
+
+                let t = cx * dx + cy * dy;
+                let perp = cx * cx + cy * cy - t * t;
+                if t < 0.0 || perp > r2 {
+                    return None;
+                }
+
+                let d = t - (r2 - perp).sqrt();
+                if d > range {
+                    return None;
+                }
+                Some((d, i))
+            })
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    /// Items of the cells a circle may touch
+    fn candidates<'a>(&'a self, p: &Position, r: f64) -> impl Iterator<Item = &'a Item> + 'a {
+        let reach = r + self.max_radius;
+        let (c0, r0) = self.cell_of(&Position {
+            x: p.x - reach,
+            y: p.y - reach,
+        });
+        let (c1, r1) = self.cell_of(&Position {
+            x: p.x + reach,
+            y: p.y + reach,
+        });
+        (r0..=r1).flat_map(move |row| {
+            (c0..=c1).flat_map(move |col| self.cells[col + row * self.cols].iter())
+        })
+    }
+
+    /// Grid cell of a position, positions outside go to the edge cells
+    fn cell_of(&self, p: &Position) -> (usize, usize) {
+        let col = (p.x / self.cell).floor().clamp(0.0, (self.cols - 1) as f64) as usize;
+        let row = (p.y / self.cell).floor().clamp(0.0, (self.rows - 1) as f64) as usize;
+        (col, row)
+    }
+}
+
+/// Signed difference of two angles in -PI..PI
+pub fn angle_between(a: f64, b: f64) -> f64 {
+    use std::f64::consts::{PI, TAU};
+    (a - b + PI).rem_euclid(TAU) - PI
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn index() -> (SpatialIndex, Vec<Entity>) {
+        let mut world = World::default();
+        let bodies: Vec<_> = [(10.0, 10.0), (12.0, 10.0), (10.0, 20.0), (90.0, 90.0)]
+            .iter()
+            .map(|&(x, y)| Body {
+                position: Position { x, y },
+                radius: 0.5,
+                ..Default::default()
+            })
+            .collect();
+        let entities: Vec<_> = bodies.iter().map(|b| world.push((b.clone(),))).collect();
+
+        let mut index = SpatialIndex::new(&Rect::new(0, 0, 100, 100), 5.0);
+        index.rebuild(entities.iter().zip(bodies.iter()).map(|(e, b)| (e, b, None)));
+        (index, entities)
+    }
+
+    #[test]
+    fn test_radius() {
+        let (index, e) = index();
+        let found: Vec<_> = index
+            .query_radius(&Position { x: 10.0, y: 10.0 }, 2.0)
+            .iter()
+            .map(|i| i.entity)
+            .collect();
+        assert_eq!(found, vec![e[0], e[1]]);
+        assert_eq!(index.query_radius(&Position { x: 50.0, y: 50.0 }, 5.0).len(), 0);
+    }
+
+    #[test]
+    fn test_cone() {
+        let (index, e) = index();
+        let p = Position { x: 10.0, y: 10.0 };
+
+        // Heading along y
+        let found: Vec<_> = index
+            .query_cone(&p, 15.0, 0.0, 0.1)
+            .iter()
+            .map(|i| i.entity)
+            .collect();
+        assert_eq!(found, vec![e[0], e[2]]);
+    }
+
+    #[test]
+    fn test_cast_ray() {
+        let (index, e) = index();
+        let p = Position { x: 10.0, y: 10.0 };
+
+        // Along x the neighbour's edge is 1.5 away
+        let (d, item) = index.cast_ray(&p, std::f64::consts::FRAC_PI_2, 10.0, e[0]).unwrap();
+        assert!((d - 1.5).abs() < 1e-9);
+        assert_eq!(item.entity, e[1]);
+
+        // Out of range and behind
+        assert!(index.cast_ray(&p, 0.0, 5.0, e[0]).is_none());
+        assert!(index.cast_ray(&p, -std::f64::consts::FRAC_PI_2, 10.0, e[0]).is_none());
+    }
+
+    #[test]
+    fn test_nearest_nest() {
+        let (mut index, e) = index();
+        assert!(index.nearest_nest(&Position { x: 0.0, y: 0.0 }, Colony(0)).is_none());
+
+        for (i, (entity, x)) in e.iter().zip([20.0, 80.0, 60.0].iter()).enumerate() {
+            index.insert(Item {
+                entity: *entity,
+                position: Position { x: *x, y: 50.0 },
+                radius: 3.0,
+                body_type: BodyType::Nest,
+                colony: Some(Colony(i / 2)),
+            });
+        }
+        let nest = index.nearest_nest(&Position { x: 70.0, y: 0.0 }, Colony(0)).unwrap();
+        assert_eq!(nest.entity, e[1]);
+
+        // Nests of other colonies are ignored
+        let nest = index.nearest_nest(&Position { x: 20.0, y: 0.0 }, Colony(1)).unwrap();
+        assert_eq!(nest.entity, e[2]);
+    }
+
+    #[test]
+    fn test_angle_between() {
+        use std::f64::consts::PI;
+        assert!((angle_between(0.1, 2.0 * PI - 0.1) - 0.2).abs() < 1e-9);
+        assert!((angle_between(-0.1, 0.1) + 0.2).abs() < 1e-9);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -7,56 +8,114 @@ use crate::creature::*;
 use crate::field::*;
 // use crate::field_render::FieldRenderer;
 use crate::resources::*;
+use crate::senses::SenseContext;
+use crate::spatial::SpatialIndex;
 use crate::utils::*;
-use evo::utils::random;
+use evo::pool::Operator;
+use evo::utils::{random, random_d};
+use evo::{Breeder, Evolver};
+use legion::query::component;
 use legion::systems::CommandBuffer;
 use legion::world::SubWorld;
 use legion::*;
 
-type AntGenetic = Genetic<AntGenome>;
+/// Rebuild the spatial index from the bodies
+#[system]
+pub fn update_index(
+    bodies: &mut Query<(Entity, &Body, Option<&Colony>)>,
+    world: &mut SubWorld,
+    #[resource] index: &mut SpatialIndex,
+) {
+    index.rebuild(bodies.iter(world));
+}
 
 #[system]
 #[read_component(Body)]
-#[read_component(AntGenetic)]
+#[read_component(Genetic<GenomeOf<E>>)]
 #[read_component(Entity)]
 #[read_component(Network)]
-#[write_component(AntGenetic)]
+#[read_component(Colony)]
+#[write_component(Genetic<GenomeOf<E>>)]
 #[write_component(Body)]
-pub fn detect(
+#[write_component(Store)]
+pub fn detect<E: ColonyEvolver>(
     objects: &mut Query<&Body>,
     world: &mut SubWorld,
     #[resource] config: &Config,
     #[resource] time: &Time,
+    #[resource] index: &SpatialIndex,
 ) {
-    let mut creatures = vec![];
-    let mut food = vec![];
-    for (entity, body, net) in <(Entity, &Body, &Network)>::query().iter(world) {
-        for (entity2, body2) in <(Entity, &Body)>::query()
-            .iter(world)
-            .filter(|b| b.1.body_type == BodyType::Food)
-        {
-            if body.touches(body2) {
-                creatures.push((*entity, body2.energy.amt));
-                food.push(*entity2);
+    let mut eating = vec![];
+    let mut returning = vec![];
+    for (entity, body, colony) in <(Entity, &Body, &Colony)>::query()
+        .filter(component::<Genetic<GenomeOf<E>>>())
+        .iter(world)
+    {
+        let mut home = None;
+        for item in index.query_radius(&body.position, body.radius) {
+            match item.body_type {
+                BodyType::Food => eating.push((*entity, item.entity)),
+                BodyType::Nest if home.is_none() && item.colony == Some(*colony) => {
+                    home = Some(item.entity)
+                }
+                _ => (),
             }
         }
+
+        // Deliver to one nest of the colony only
+        if let Some(nest) = home {
+            returning.push((*entity, nest));
+        }
     }
 
-    // body.energy.amt += body2.energy.amt;
-    // body2.energy.amt = 0.0;
+    for (entity, food) in eating.into_iter() {
+        // Take a bite out of the food and pick up what can still be carried
+        let carrying = <&Body>::query().get(world, entity).unwrap().carrying;
+        let f = <&mut Body>::query().get_mut(world, food).unwrap();
+        let amt = f.energy.amt.min(config.bite * time.dt);
+        let load = (f.energy.amt - amt).min(config.carry - carrying).max(0.0);
+        if amt + load <= 0.0 {
+            continue;
+        }
+        f.energy.amt -= amt + load;
+
+        let b = <&mut Body>::query().get_mut(world, entity).unwrap();
+        b.energy.amt += amt;
+        b.carrying += load;
+    }
 
-    for (entity, amt) in creatures.into_iter() {
-        let (b, g) = <(&mut Body, &mut AntGenetic)>::query()
+    for (entity, nest) in returning.into_iter() {
+        let (b, g) = <(&mut Body, &mut Genetic<GenomeOf<E>>)>::query()
             .get_mut(world, entity)
             .unwrap();
-        // b.energy.amt += amt;
-        // g.fitness += 1.0;
+        let load = b.carrying;
+        if load <= 0.0 {
+            continue;
+        }
+        b.carrying = 0.0;
+
+        // Food brought home is rewarded
+        g.fitness += load;
+
+        let store = <&mut Store>::query().get_mut(world, nest).unwrap();
+        store.energy += load;
+        store.deliveries += 1;
     }
+}
 
-    for entity in food.into_iter() {
-        let b = <&mut Body>::query().get_mut(world, entity).unwrap();
-        b.position = Position::random(&config.bounds);
+/// Eaten up food moves elsewhere, food regrows up to its content
+#[system(for_each)]
+pub fn regrow_food(body: &mut Body, #[resource] time: &Time, #[resource] config: &Config) {
+    if body.body_type != BodyType::Food {
+        return;
     }
+
+    if body.energy.amt <= 0.0 {
+        body.energy.amt = 0.0;
+        body.position = config.food_position();
+    }
+
+    body.energy.amt = (body.energy.amt + config.food_regrow * time.dt).min(config.food_energy);
 }
 
 #[system(for_each)]
@@ -66,7 +125,7 @@ pub fn update_emitters(
     #[resource] field: &mut FieldArr,
     #[resource] config: &Config,
 ) {
-    if body.emits.len() == 0 {
+    if body.emits.iter().all(|e| *e <= 0.0) {
         return;
     }
 
@@ -76,37 +135,49 @@ pub fn update_emitters(
     field.set(x, y, &body.emits);
 }
 
+/// Cast the vision rays of creatures
 #[system(for_each)]
-pub fn update_networks(
+pub fn update_vision(
+    entity: &Entity,
+    body: &Body,
+    vision: &mut Vision,
+    #[resource] index: &SpatialIndex,
+    #[resource] config: &Config,
+) {
+    vision.look(*entity, body, index, &config.bounds, config.map.as_ref());
+}
+
+#[system(for_each)]
+pub fn update_networks<E: ColonyEvolver>(
     net: &mut Network,
-    genes: &mut AntGenetic,
+    genes: &mut Genetic<GenomeOf<E>>,
     body: &mut Body,
+    colony: &Colony,
+    vision: Option<&Vision>,
     #[resource] config: &Config,
     #[resource] time: &Time,
     #[resource] field: &mut FieldArr,
+    #[resource] index: &SpatialIndex,
 ) {
-    let x = body.position.x / config.bounds.width as f64;
-    let y = 1.0 - body.position.y / config.bounds.height as f64;
-
-    let (cx, cy) = field.get_dx(x, y);
-    // let inputs = vec![ cx, cy, 0.0]
-        // if (5.0 * time.elapsed).sin() > 0.75 {
-        //     1.0 } else { 0.0
-        // }, 0.0, 0.0];
-    let inputs = vec![cx.r, cy.r, 0.0];
-    // let inputs = vec![x - 0.5, y - 0.5];
+    // Read the sensors of the body plan
+    let inputs = config.sense(&SenseContext {
+        body,
+        colony: *colony,
+        vision,
+        field,
+        time,
+        config,
+        index,
+    });
     let (out, state) = net.network.activate(inputs.clone(), net.state.clone(), time.dt);
     net.state = state;
 
     // body.color.r = field.get(x, y).r;
-    body.color.r = inputs[0];
+    body.color.r = inputs.first().cloned().unwrap_or(0.0);
     // body.color.g = ((5.0 * time.elapsed).sin() + 1.) / 2.;
 
-    // body.theta += out[0] * time.dt * 1.;
-    // body.theta += time.dt * 0.5;
-    // body.position = body.position.advance(out[1] * time.dt * 0.1, body.theta);
-    body.position.x += out[0] * time.dt;
-    body.position.y += out[1] * time.dt;
+    // Apply the actuators
+    config.act(&out, body, time.dt);
 
     // let emit = Color {
     //     r: out[2].clamp(0.0, 1.0) * 10.0,
@@ -115,8 +186,7 @@ pub fn update_networks(
     //     a: 1.0
     // };
     // body.emits = vec![0.0, body.color.g * 0.5, body.color.b * 0.5, 1.0];
-    body.emits = body.color.into();
-    genes.fitness += field.get(x, y).r;
+    // genes.fitness += field.get(x, y).r;
     // genes.fitness -= (out[0] - (5.0 * time.elapsed).cos()).powi(2);
     // genes.fitness -= (out[1] - (5.0 * time.elapsed).sin()).powi(2);
 
@@ -127,39 +197,492 @@ pub fn update_networks(
     body.position = body.position.clamp(&config.bounds);
 }
 
+/// Move bodies with their velocity, slowed by drag and capped at the max speed
 #[system(for_each)]
-pub fn update_energy(b: &mut Body, g: &mut AntGenetic, #[resource] time: &Time) {
+pub fn update_motion(body: &mut Body, #[resource] time: &Time, #[resource] config: &Config) {
+    let l = &config.locomotion;
+    let speed = body.speed();
+    if speed > l.max_speed {
+        body.velocity.0 *= l.max_speed / speed;
+        body.velocity.1 *= l.max_speed / speed;
+    }
+
+    body.position += (body.velocity.0 * time.dt, body.velocity.1 * time.dt);
+    config.collide(body);
+
+    let drag = (1.0 - l.drag * time.dt).max(0.0);
+    body.velocity.0 *= drag;
+    body.velocity.1 *= drag;
+}
+
+/// Push overlapping creatures apart and out of obstacles
+#[system]
+#[write_component(Body)]
+pub fn resolve_collisions(
+    bodies: &mut Query<(Entity, &mut Body)>,
+    world: &mut SubWorld,
+    #[resource] config: &Config,
+    #[resource] index: &SpatialIndex,
+) {
+    let physics = &config.physics;
+    let solid = |t: BodyType| t == BodyType::Creature || t == BodyType::Obstacle;
+
+    // The index is from the start of the tick, bodies have moved since
+    let current: HashMap<Entity, (Position, f64, BodyType)> = bodies
+        .iter(world)
+        .filter(|(_, b)| solid(b.body_type))
+        .map(|(e, b)| (*e, (b.position, b.radius, b.body_type)))
+        .collect();
+
+    let mut pushes = vec![];
+    for (entity, &(position, radius, body_type)) in current.iter() {
+        if body_type != BodyType::Creature {
+            continue;
+        }
+
+        let body = Body {
+            position,
+            radius,
+            ..Default::default()
+        };
+        for item in index.query_radius(&position, 2.0 * radius) {
+            let (p, r, t) = match current.get(&item.entity) {
+                Some(other) if item.entity != *entity => other,
+                _ => continue,
+            };
+            if let Some((normal, depth)) = body.overlap(p, *r) {
+                match t {
+                    // Each creature of a pair moves half way
+                    BodyType::Creature => {
+                        pushes.push((*entity, normal, 0.5 * depth * physics.push, false))
+                    }
+                    _ => pushes.push((*entity, normal, depth, true)),
+                }
+            }
+        }
+    }
+
+    for (entity, normal, depth, obstacle) in pushes.into_iter() {
+        if let Ok((_, body)) = bodies.get_mut(world, entity) {
+            body.position += (normal.0 * depth, normal.1 * depth);
+            if obstacle {
+                body.hit(normal, physics.walls);
+            }
+            config.collide(body);
+        }
+    }
+}
+
+#[system(for_each)]
+pub fn update_energy<E: ColonyEvolver>(
+    b: &mut Body,
+    g: &mut Genetic<GenomeOf<E>>,
+    #[resource] time: &Time,
+) {
     b.energy.amt -= b.energy.decay * time.dt * (0.2 * random() + 0.8);
     if b.energy.amt < 0.0 {
         g.alive = false;
     }
 }
 
+/// Creatures with enough energy breed with a nearby mate of their colony or alone,
+/// the offspring starts next to the parent with part of its energy
 #[system]
-pub fn remove_dead(
-    objects: &mut Query<(Entity, &AntGenetic, &Body)>,
+#[read_component(Genetic<GenomeOf<E>>)]
+#[read_component(Colony)]
+#[write_component(Body)]
+pub fn reproduce<E: ColonyEvolver>(
+    objects: &mut Query<(Entity, &Genetic<GenomeOf<E>>, &Body, &Colony)>,
     commands: &mut CommandBuffer,
     world: &mut SubWorld,
-    #[resource] pool: &mut AntPool,
+    #[resource] colonies: &mut Colonies<E>,
     #[resource] config: &Config,
+    #[resource] index: &SpatialIndex,
 ) {
-    for (entity, gen, body) in objects.iter(world) {
+    let r = match &config.reproduction {
+        Some(r) => r,
+        None => return,
+    };
+
+    let mut counts: HashMap<Colony, usize> = HashMap::new();
+    let mut parents = vec![];
+    for (entity, gen, body, colony) in objects.iter(world) {
+        *counts.entry(*colony).or_default() += gen.alive as usize;
+        if gen.alive && body.energy.amt >= r.energy {
+            parents.push((*entity, *colony, gen.species_id, gen.genome.clone(), body.position));
+        }
+    }
+
+    for (entity, colony, id, genome, position) in parents.into_iter() {
+        let count = counts.entry(colony).or_default();
+        if *count >= r.max_population {
+            continue;
+        }
+        let evolver = match colonies.get_mut(colony) {
+            Some(evolver) => evolver,
+            None => continue,
+        };
+
+        // Mate with the closest living creature of the same colony
+        let mut mate = None;
+        let mut closest = f64::INFINITY;
+        for item in index.query_radius(&position, r.mate_radius) {
+            if item.entity == entity || item.body_type != BodyType::Creature {
+                continue;
+            }
+            if let Ok((_, g, b, c)) = objects.get(world, item.entity) {
+                let d = b.position.dist_sq(&position);
+                if g.alive && *c == colony && d < closest {
+                    closest = d;
+                    mate = Some((g.species_id, g.genome.clone()));
+                }
+            }
+        }
+
+        let (parents, operator, child) = match mate {
+            Some((mate_id, mate)) => (
+                vec![id, mate_id],
+                Operator::Cross,
+                evolver.breeder().breed(&genome, &mate),
+            ),
+            None => (vec![id], Operator::Mutate, evolver.breeder().mutate(&genome)),
+        };
+
+        let b = <&mut Body>::query().get_mut(world, entity).unwrap();
+        b.energy.amt -= r.cost;
+
+        let id = evolver.adopt(parents, operator);
+        let mut child = E::Breeder::spawn(colony, id, child, config);
+        child.0.position = (position + (random_d(1.0), random_d(1.0))).clamp(&config.bounds);
+        child.0.energy.amt = r.cost;
+        commands.push(child);
+        *count += 1;
+    }
+}
+
+/// Report dead creatures to the evolver of their colony,
+/// which replaces them unless the population breeds in the world
+#[system]
+pub fn remove_dead<E: ColonyEvolver>(
+    objects: &mut Query<(Entity, &Genetic<GenomeOf<E>>, &Body, &Colony)>,
+    commands: &mut CommandBuffer,
+    world: &mut SubWorld,
+    #[resource] colonies: &mut Colonies<E>,
+    #[resource] config: &Config,
+) {
+    let mut alive: HashMap<Colony, usize> = HashMap::new();
+    for (_, gen, _, colony) in objects.iter(world) {
+        *alive.entry(*colony).or_default() += gen.alive as usize;
+    }
+
+    let mut replaced: HashMap<Colony, usize> = HashMap::new();
+    for (entity, gen, body, colony) in objects.iter(world) {
         if !gen.alive {
+            commands.remove(*entity);
+            let evolver = match colonies.get_mut(*colony) {
+                Some(evolver) => evolver,
+                None => continue,
+            };
+
             let fitness = random() * 0.00000001;
             // let fitness = vec![body.color.r, body.color.g, body.color.b]
             //     .into_iter()
             //     .max_by(|a, b| a.partial_cmp(b).unwrap())
             //     .unwrap() as f64;
 
-            pool.report(
+            evolver.report_fitness(
                 gen.species_id,
                 gen.genome.clone(),
                 fitness as f64 + gen.fitness as f64,
             );
-            commands.remove(*entity);
 
-            let (id, gene) = pool.next().unwrap();
-            commands.push(Creature::new(id, gene, config));
+            let replace = match &config.reproduction {
+                Some(r) => r.min_population.saturating_sub(alive[colony]),
+                None => usize::MAX,
+            };
+            let replaced = replaced.entry(*colony).or_default();
+            if *replaced < replace {
+                *replaced += 1;
+                let (id, gene) = evolver.next_genome();
+                commands.push(E::Breeder::spawn(*colony, id, gene, config));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::senses::{Actuator, Sensor};
+    use evo::Breeder;
+
+    fn test_config() -> Config {
+        Config {
+            food_energy: 3.0,
+            sensors: vec![Sensor::Energy],
+            actuators: vec![Actuator::Drive],
+            ..Config::default()
+        }
+    }
+
+    /// Ant of a colony in the middle carrying a load
+    fn push_ant(world: &mut World, config: &Config, colony: Colony, carrying: f64) -> Entity {
+        let mut ant = AntBreeder::spawn(colony, 1, AntBreeder::new(config).random(), config);
+        ant.0.position = Position { x: 50.0, y: 50.0 };
+        ant.0.carrying = carrying;
+        world.push(ant)
+    }
+
+    /// World with an ant of the first colony
+    fn ant_world(config: &Config, carrying: f64) -> (World, Entity) {
+        let mut world = World::default();
+        let ant = push_ant(&mut world, config, Colony(0), carrying);
+        (world, ant)
+    }
+
+    /// Resources of a world ticking at 0.1s
+    fn test_resources(config: Config) -> Resources {
+        let mut resources = Resources::default();
+        resources.insert(SpatialIndex::new(&config.bounds, 5.0));
+        resources.insert(config);
+        resources.insert(Time {
+            dt: 0.1,
+            elapsed: 0.0,
+        });
+        resources
+    }
+
+    /// Run detection for one tick
+    fn run_detect(world: &mut World, config: Config) {
+        let mut resources = test_resources(config);
+        let mut schedule = Schedule::builder()
+            .add_system(update_index_system())
+            .add_system(detect_system::<AntPool>())
+            .build();
+        schedule.execute(world, &mut resources);
+    }
+
+    /// Food of some energy on top of the ant
+    fn push_food(world: &mut World, config: &Config, energy: f64) -> Entity {
+        let mut food = Food::with_scent(1.0, config);
+        food.0.position = Position { x: 50.0, y: 50.0 };
+        food.0.energy.amt = energy;
+        world.push(food)
+    }
+
+    #[test]
+    fn test_detect() {
+        // Two overlapping foods only fill the ant up once
+        let config = test_config();
+        let (mut world, ant) = ant_world(&config, 0.0);
+        for dx in [-0.2, 0.2].iter() {
+            let mut food = Food::with_scent(1.0, &config);
+            food.0.position = Position { x: 50.0 + dx, y: 50.0 };
+            world.push(food);
+        }
+        let carry = config.carry;
+        run_detect(&mut world, config);
+        let body = <&Body>::query().get(&world, ant).unwrap();
+        assert!((body.carrying - carry).abs() < 1e-9);
+
+        // Two overlapping nests get a single delivery
+        let config = test_config();
+        let (mut world, ant) = ant_world(&config, 1.0);
+        let nests: Vec<_> = [49.0, 51.0]
+            .iter()
+            .map(|x| world.push(Nest::new(Position { x: *x, y: 50.0 }, Colony(0))))
+            .collect();
+        run_detect(&mut world, config);
+
+        let stores: Vec<Store> = nests
+            .iter()
+            .map(|n| *<&Store>::query().get(&world, *n).unwrap())
+            .collect();
+        assert_eq!(stores.iter().map(|s| s.deliveries).sum::<usize>(), 1);
+        assert_eq!(stores.iter().map(|s| s.energy).sum::<f64>(), 1.0);
+
+        let (body, gen) = <(&Body, &Genetic<AntGenome>)>::query()
+            .get(&world, ant)
+            .unwrap();
+        assert_eq!(body.carrying, 0.0);
+        assert_eq!(gen.fitness, 1.0);
+    }
+
+    #[test]
+    fn test_food_depletes() {
+        // The ant eats the last of the food, which moves elsewhere
+        let config = test_config();
+        let (mut world, ant) = ant_world(&config, 0.0);
+        let energy = <&Body>::query().get(&world, ant).unwrap().energy.amt;
+        let food = push_food(&mut world, &config, 0.05);
+
+        let mut resources = test_resources(config);
+        let mut schedule = Schedule::builder()
+            .add_system(update_index_system())
+            .add_system(detect_system::<AntPool>())
+            .add_system(regrow_food_system())
+            .build();
+        schedule.execute(&mut world, &mut resources);
+
+        let f = <&Body>::query().get(&world, food).unwrap();
+        assert_eq!(f.energy.amt, 0.0);
+        assert_ne!(f.position, Position { x: 50.0, y: 50.0 });
+
+        let b = <&Body>::query().get(&world, ant).unwrap();
+        assert!((b.energy.amt - energy - 0.05).abs() < 1e-9);
+        assert_eq!(b.carrying, 0.0);
+    }
+
+    #[test]
+    fn test_food_regrows() {
+        let config = Config {
+            food_regrow: 1.0,
+            ..test_config()
+        };
+        let food_energy = config.food_energy;
+        let mut world = World::default();
+        let food = push_food(&mut world, &config, 2.5);
+
+        let mut resources = test_resources(config);
+        let mut schedule = Schedule::builder().add_system(regrow_food_system()).build();
+        let energy = |world: &World| <&Body>::query().get(world, food).unwrap().energy.amt;
+
+        // 0.1 per tick, up to the full content
+        for _ in 0..3 {
+            schedule.execute(&mut world, &mut resources);
+        }
+        assert!((energy(&world) - 2.8).abs() < 1e-9);
+        for _ in 0..10 {
+            schedule.execute(&mut world, &mut resources);
         }
+        assert_eq!(energy(&world), food_energy);
+        assert_eq!(
+            <&Body>::query().get(&world, food).unwrap().position,
+            Position { x: 50.0, y: 50.0 }
+        );
+    }
+
+    #[test]
+    fn test_delivery() {
+        // The load moves into the nest's store and counts as fitness, not as the ant's energy
+        let config = test_config();
+        let (mut world, ant) = ant_world(&config, 0.5);
+        let energy = <&Body>::query().get(&world, ant).unwrap().energy.amt;
+        let nest = world.push(Nest::new(Position { x: 50.0, y: 50.0 }, Colony(0)));
+        run_detect(&mut world, config);
+
+        let store = <&Store>::query().get(&world, nest).unwrap();
+        assert_eq!(store.energy, 0.5);
+        assert_eq!(store.deliveries, 1);
+
+        let (b, g) = <(&Body, &Genetic<AntGenome>)>::query()
+            .get(&world, ant)
+            .unwrap();
+        assert_eq!(b.carrying, 0.0);
+        assert_eq!(b.energy.amt, energy);
+        assert_eq!(g.fitness, 0.5);
+    }
+
+    /// Run reproduction for one tick on ants of some energies, far apart,
+    /// returns the ants with their species ids and the resources
+    fn run_reproduce(
+        world: &mut World,
+        config: Config,
+        energies: &[f64],
+    ) -> (Vec<(Entity, i32)>, Resources) {
+        let mut pool = AntPool::new(10, AntBreeder::new(&config));
+        let ants = energies
+            .iter()
+            .enumerate()
+            .map(|(i, energy)| {
+                let (id, g) = pool.next_genome();
+                let mut ant = AntBreeder::spawn(Colony(0), id, g, &config);
+                ant.0.position = Position {
+                    x: 20.0 + 30.0 * i as f64,
+                    y: 50.0,
+                };
+                ant.0.energy.amt = *energy;
+                (world.push(ant), id)
+            })
+            .collect();
+
+        let mut colonies = Colonies::default();
+        colonies.insert(Colony(0), pool);
+        let mut resources = test_resources(config);
+        resources.insert(colonies);
+
+        let mut schedule = Schedule::builder()
+            .add_system(update_index_system())
+            .add_system(reproduce_system::<AntPool>())
+            .build();
+        schedule.execute(world, &mut resources);
+        (ants, resources)
+    }
+
+    #[test]
+    fn test_reproduce() {
+        let r = Reproduction::default();
+        let config = Config {
+            reproduction: Some(r),
+            ..test_config()
+        };
+        let mut world = World::default();
+        let energies = [r.energy + 1.0, r.energy - 1.0];
+        let (ants, resources) = run_reproduce(&mut world, config, &energies);
+
+        // Only the ant above the threshold breeds, alone, and hands over part of its energy
+        let energy = |e| <&Body>::query().get(&world, e).unwrap().energy.amt;
+        assert_eq!(energy(ants[0].0), r.energy + 1.0 - r.cost);
+        assert_eq!(energy(ants[1].0), r.energy - 1.0);
+
+        let creatures: Vec<_> = <(Entity, &Body, &Genetic<AntGenome>)>::query()
+            .iter(&world)
+            .filter(|(e, _, _)| ants.iter().all(|a| a.0 != **e))
+            .collect();
+        assert_eq!(creatures.len(), 1);
+        let (_, child, gen) = creatures[0];
+        assert_eq!(child.energy.amt, r.cost);
+        assert!(child.position.dist_sq(&Position { x: 20.0, y: 50.0 }) <= 2.0);
+
+        // The offspring is a mutation of its parent
+        let colonies = resources.get::<Colonies<AntPool>>().unwrap();
+        let record = colonies.get(Colony(0)).unwrap().lineage.get(gen.species_id).unwrap();
+        assert_eq!(record.parents, vec![ants[0].1]);
+        assert_eq!(record.operator, Operator::Mutate);
+    }
+
+    #[test]
+    fn test_reproduce_disabled() {
+        let config = test_config();
+        let mut world = World::default();
+        let (ants, _) = run_reproduce(&mut world, config, &[10.0, 1.0]);
+
+        assert_eq!(<&Body>::query().iter(&world).count(), 2);
+        assert_eq!(<&Body>::query().get(&world, ants[0].0).unwrap().energy.amt, 10.0);
+    }
+
+    #[test]
+    fn test_colonies() {
+        // Two colonies of the same breeder, each ant on top of the first colony's nest
+        let config = test_config();
+        let mut world = World::default();
+        let ants: Vec<_> = (0..2)
+            .map(|i| push_ant(&mut world, &config, Colony(i), 1.0))
+            .collect();
+        let nests: Vec<_> = [(50.0, 0), (90.0, 1)]
+            .iter()
+            .map(|(x, i)| world.push(Nest::new(Position { x: *x, y: 50.0 }, Colony(*i))))
+            .collect();
+        run_detect(&mut world, config);
+
+        // Only the first colony's ant delivers and credits its own nest
+        let store = |n| *<&Store>::query().get(&world, n).unwrap();
+        assert_eq!(store(nests[0]).energy, 1.0);
+        assert_eq!(store(nests[0]).deliveries, 1);
+        assert_eq!(store(nests[1]), Store::default());
+
+        let carrying = |a| <&Body>::query().get(&world, a).unwrap().carrying;
+        assert_eq!(carrying(ants[0]), 0.0);
+        assert_eq!(carrying(ants[1]), 1.0);
     }
 }
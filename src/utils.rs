@@ -248,8 +248,9 @@ impl Position {
         (self.x - p.x).powi(2) + (self.y - p.y).powi(2)
     }
 
+    /// Heading from this position to another, in the convention of advance
     pub fn atan2(&self, p: &Position) -> f64 {
-        (self.y - p.y).atan2(self.y - p.y)
+        (p.x - self.x).atan2(p.y - self.y)
     }
 
     pub fn clamp(&self, rect: &Rect) -> Self {